@@ -0,0 +1,170 @@
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::error::ApiResult;
+use super::types::{
+    GraphQlResponse, LoginData, SearchData, SearchResult, Session, User, UserData,
+};
+use crate::http_source::build_useragent;
+use crate::station::Station;
+
+const GRAPHQL_URL: &str = "https://listen.moe/graphql";
+const SEARCH_LIMIT: u32 = 50;
+
+const LOGIN_QUERY: &str = r#"
+mutation login($username: String!, $password: String!) {
+    login(username: $username, password: $password) {
+        token
+        user { username displayName requestsRemaining }
+    }
+}"#;
+
+const USER_QUERY: &str = r#"
+query user($username: String!) {
+    user(username: $username) { username displayName requestsRemaining }
+}"#;
+
+const SEARCH_QUERY: &str = r#"
+query search($query: String!, $limit: Int) {
+    search(query: $query, limit: $limit) {
+        ... on Song {
+            id
+            title
+            duration
+            artists { name nameRomaji }
+            albums { name nameRomaji }
+        }
+    }
+}"#;
+
+const REQUEST_QUERY: &str = r#"
+mutation requestSong($id: Int!, $kpop: Boolean) {
+    requestSong(id: $id, kpop: $kpop) { id }
+}"#;
+
+/// Blocking client for the LISTEN.moe GraphQL API. Cheap to clone, so every
+/// worker thread can take its own copy.
+#[derive(Debug, Clone)]
+pub struct Api {
+    client: Client,
+    useragent: String,
+}
+
+impl Api {
+    pub fn new() -> ApiResult<Self> {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(15))
+            .build()?;
+        Ok(Self {
+            client,
+            useragent: build_useragent(),
+        })
+    }
+
+    pub fn login(&self, username: &str, password: &str) -> ApiResult<Session> {
+        let data: LoginData = self.query(
+            None,
+            LOGIN_QUERY,
+            json!({ "username": username, "password": password }),
+        )?;
+        Ok(Session {
+            token: data.login.token,
+            user: data.login.user,
+        })
+    }
+
+    /// Refresh the user, mainly to read the remaining request quota.
+    pub fn user(&self, session: &Session) -> ApiResult<User> {
+        let data: UserData = self.query(
+            Some(&session.token),
+            USER_QUERY,
+            json!({ "username": session.user.username }),
+        )?;
+        Ok(data.user)
+    }
+
+    pub fn search(&self, session: Option<&Session>, query: &str) -> ApiResult<Vec<SearchResult>> {
+        let data: SearchData = self.query(
+            session.map(|s| s.token.as_str()),
+            SEARCH_QUERY,
+            json!({ "query": query, "limit": SEARCH_LIMIT }),
+        )?;
+
+        Ok(data
+            .search
+            .into_iter()
+            .filter_map(|song| {
+                let id = song.id?;
+                let artist = song
+                    .artists
+                    .iter()
+                    .filter_map(|a| a.display())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some(SearchResult {
+                    id,
+                    title: song.title.unwrap_or_else(|| "unknown title".to_owned()),
+                    artist,
+                    album: song
+                        .albums
+                        .first()
+                        .and_then(|a| a.display())
+                        .map(str::to_owned),
+                    duration_secs: song.duration.unwrap_or(0),
+                })
+            })
+            .collect())
+    }
+
+    pub fn request_song(&self, session: &Session, song_id: u32, station: Station) -> ApiResult<()> {
+        let _: Value = self.query(
+            Some(&session.token),
+            REQUEST_QUERY,
+            json!({ "id": song_id, "kpop": matches!(station, Station::Kpop) }),
+        )?;
+        Ok(())
+    }
+
+    fn query<T: DeserializeOwned>(
+        &self,
+        token: Option<&str>,
+        query: &str,
+        variables: Value,
+    ) -> ApiResult<T> {
+        let body = serde_json::to_vec(&json!({ "query": query, "variables": variables }))?;
+
+        let mut req = self
+            .client
+            .post(GRAPHQL_URL)
+            .header("User-Agent", &self.useragent)
+            .header("Content-Type", "application/json")
+            .body(body);
+        if let Some(token) = token {
+            req = req.bearer_auth(token);
+        }
+
+        let resp = req.send()?;
+        let status = resp.status();
+        let bytes = resp.bytes()?;
+
+        // GraphQL reports most failures (bad login, quota used up, …) in `errors`,
+        // sometimes with a non-success status, so look at the body first.
+        let parsed: GraphQlResponse<T> = match serde_json::from_slice(&bytes) {
+            Ok(parsed) => parsed,
+            Err(_) if !status.is_success() => {
+                return Err(format!("HTTP status {status}").into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(first) = parsed.errors.first() {
+            return Err(first.message.clone().into());
+        }
+        parsed
+            .data
+            .ok_or_else(|| format!("empty response (HTTP status {status})").into())
+    }
+}
@@ -0,0 +1,4 @@
+//! Common error/result types for the LISTEN.moe API client.
+
+pub type ApiError = Box<dyn std::error::Error + Send + Sync + 'static>;
+pub type ApiResult<T> = Result<T, ApiError>;
@@ -0,0 +1,7 @@
+mod client;
+mod error;
+mod types;
+
+pub use crate::http_source::build_useragent;
+pub use client::Api;
+pub use types::{SearchResult, Session, User};
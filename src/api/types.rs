@@ -0,0 +1,95 @@
+use serde::Deserialize;
+
+/// Logged-in user, including the remaining daily request quota.
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub username: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "requestsRemaining", default)]
+    pub requests_remaining: Option<u32>,
+}
+
+impl User {
+    pub fn name(&self) -> &str {
+        self.display_name
+            .as_deref()
+            .filter(|n| !n.is_empty())
+            .unwrap_or(&self.username)
+    }
+}
+
+/// Token plus the user it belongs to.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub token: String,
+    pub user: User,
+}
+
+/// A song from the library search, flattened for display.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub id: u32,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration_secs: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct GraphQlResponse<T> {
+    pub(super) data: Option<T>,
+    #[serde(default)]
+    pub(super) errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct GraphQlError {
+    pub(super) message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginData {
+    pub(super) login: LoginPayload,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginPayload {
+    pub(super) token: String,
+    pub(super) user: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct UserData {
+    pub(super) user: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SearchData {
+    #[serde(default)]
+    pub(super) search: Vec<ApiSong>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiSong {
+    pub(super) id: Option<u32>,
+    pub(super) title: Option<String>,
+    #[serde(default)]
+    pub(super) artists: Vec<ApiNamed>,
+    #[serde(default)]
+    pub(super) albums: Vec<ApiNamed>,
+    pub(super) duration: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiNamed {
+    pub(super) name: Option<String>,
+    #[serde(rename = "nameRomaji")]
+    pub(super) name_romaji: Option<String>,
+}
+
+impl ApiNamed {
+    pub(super) fn display(&self) -> Option<&str> {
+        self.name.as_deref().or(self.name_romaji.as_deref())
+    }
+}
@@ -0,0 +1,361 @@
+use dirs_next as dirs;
+use log::warn;
+use reqwest::blocking::Client;
+use reqwest::header::{
+    HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT,
+};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::atomic_file::write_atomic;
+use listenmoe::api::build_useragent;
+use listenmoe::APP_ID;
+
+/// Evict the least recently used covers above this size.
+const MAX_BYTES: u64 = 64 * 1024 * 1024;
+/// Serve a cover without asking the server again for this long.
+const FRESH_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+
+type CacheResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Sidecar stored next to each cached image.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix time of the last download or successful revalidation.
+    validated: u64,
+    /// Colors extracted from the image, so they aren't computed again.
+    tint: Option<serde_json::Value>,
+}
+
+enum Download {
+    NotModified,
+    Body {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// `covers` in the user cache dir.
+fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|base| base.join(APP_ID).join("covers"))
+}
+
+/// FNV-1a, so file names stay the same across builds.
+fn key(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+fn paths(url: &str) -> Option<(PathBuf, PathBuf)> {
+    let dir = cache_dir()?;
+    let key = key(url);
+    Some((
+        dir.join(format!("{key}.img")),
+        dir.join(format!("{key}.json")),
+    ))
+}
+
+fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Cover bytes for `url`, from the cache when possible. Stale entries are
+/// revalidated with ETag/Last-Modified, and served as is when the server can't
+/// be reached.
+pub fn fetch(url: &str) -> CacheResult<Vec<u8>> {
+    let cached = load(url);
+    if let Some((entry, bytes)) = &cached {
+        if now_secs().saturating_sub(entry.validated) < FRESH_FOR.as_secs() {
+            touch(url);
+            return Ok(bytes.clone());
+        }
+    }
+
+    match download(url, cached.as_ref().map(|(entry, _)| entry)) {
+        Ok(Download::NotModified) => {
+            let Some((mut entry, bytes)) = cached else {
+                return Err("Unexpected 304 for an uncached cover".into());
+            };
+            entry.validated = now_secs();
+            if let Err(err) = save_entry(url, &entry) {
+                warn!("Failed to update cover cache entry: {err}");
+            }
+            touch(url);
+            Ok(bytes)
+        }
+        Ok(Download::Body {
+            bytes,
+            etag,
+            last_modified,
+        }) => {
+            // Keep the colors when the server sent the same image again.
+            let tint = cached
+                .filter(|(_, old)| *old == bytes)
+                .and_then(|(entry, _)| entry.tint);
+            let entry = Entry {
+                url: url.to_owned(),
+                etag,
+                last_modified,
+                validated: now_secs(),
+                tint,
+            };
+            if let Err(err) = store(url, &entry, &bytes) {
+                warn!("Failed to cache cover: {err}");
+            }
+            evict();
+            Ok(bytes)
+        }
+        Err(err) => match cached {
+            Some((_, bytes)) => {
+                warn!("Using cached cover, revalidation failed: {err}");
+                Ok(bytes)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+fn download(url: &str, cached: Option<&Entry>) -> CacheResult<Download> {
+    let mut request = client().get(url).header(USER_AGENT, build_useragent());
+    if let Some(entry) = cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = request.send()?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Download::NotModified);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("Non-success status: {}", resp.status()).into());
+    }
+    let header = |name: HeaderName| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let bytes = resp.bytes()?.to_vec();
+    Ok(Download::Body {
+        bytes,
+        etag,
+        last_modified,
+    })
+}
+
+fn load(url: &str) -> Option<(Entry, Vec<u8>)> {
+    let (img, _) = paths(url)?;
+    let entry = load_entry(url)?;
+    let bytes = fs::read(img).ok()?;
+    Some((entry, bytes))
+}
+
+fn load_entry(url: &str) -> Option<Entry> {
+    let (_, meta) = paths(url)?;
+    let entry: Entry = serde_json::from_slice(&fs::read(meta).ok()?).ok()?;
+    // Guard against hash collisions.
+    (entry.url == url).then_some(entry)
+}
+
+fn save_entry(url: &str, entry: &Entry) -> io::Result<()> {
+    let (_, meta) = paths(url).ok_or_else(|| io::Error::other("no cache dir"))?;
+    let text = serde_json::to_vec(entry).map_err(io::Error::other)?;
+    write_atomic(&meta, &text)
+}
+
+fn store(url: &str, entry: &Entry, bytes: &[u8]) -> io::Result<()> {
+    let (img, _) = paths(url).ok_or_else(|| io::Error::other("no cache dir"))?;
+    if let Some(dir) = img.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // The image first: an entry is only ever read next to a complete image
+    write_atomic(&img, bytes)?;
+    save_entry(url, entry)
+}
+
+/// Mark the cover as recently used; eviction goes by the image's mtime.
+fn touch(url: &str) {
+    if let Some((img, _)) = paths(url) {
+        if let Ok(file) = fs::File::options().write(true).open(img) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+    }
+}
+
+fn evict() {
+    if let Some(dir) = cache_dir() {
+        evict_in(&dir, MAX_BYTES);
+    }
+}
+
+/// Drop the least recently used covers in `dir` until their images fit in `max_bytes`.
+fn evict_in(dir: &Path, max_bytes: u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut images: Vec<(SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "img"))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some((meta.modified().ok()?, meta.len(), e.path()))
+        })
+        .collect();
+    let mut total: u64 = images.iter().map(|(_, len, _)| len).sum();
+    if total <= max_bytes {
+        return;
+    }
+
+    images.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in images {
+        if total <= max_bytes {
+            break;
+        }
+        let _ = fs::remove_file(path.with_extension("json"));
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+        }
+    }
+}
+
+/// The colors stored with the cover by `set_tint`.
+pub fn tint<T: DeserializeOwned>(url: &str) -> Option<T> {
+    serde_json::from_value(load_entry(url)?.tint?).ok()
+}
+
+pub fn set_tint<T: Serialize>(url: &str, tint: &T) {
+    let Some(mut entry) = load_entry(url) else {
+        return;
+    };
+    entry.tint = serde_json::to_value(tint).ok();
+    if let Err(err) = save_entry(url, &entry) {
+        warn!("Failed to cache cover colors: {err}");
+    }
+}
+
+/// Total size of the cached files in bytes.
+pub fn size() -> u64 {
+    cache_dir().map_or(0, |dir| dir_size(&dir))
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.metadata().ok())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+pub fn clear() -> io::Result<()> {
+    let Some(dir) = cache_dir() else {
+        return Ok(());
+    };
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// An empty folder of its own for each test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("listenmoe-covers-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A cover of `len` bytes with its sidecar, last used `age` seconds ago.
+    fn add_cover(dir: &Path, key: &str, len: usize, age: u64) {
+        let img = dir.join(format!("{key}.img"));
+        fs::write(&img, vec![0; len]).unwrap();
+        fs::write(dir.join(format!("{key}.json")), b"{}").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(img)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    fn has_cover(dir: &Path, key: &str) -> bool {
+        dir.join(format!("{key}.img")).exists() && dir.join(format!("{key}.json")).exists()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let dir = temp_dir("lru");
+        add_cover(&dir, "new", 100, 10);
+        add_cover(&dir, "old", 100, 30);
+        add_cover(&dir, "mid", 100, 20);
+        evict_in(&dir, 150);
+        assert!(!has_cover(&dir, "old"));
+        assert!(!dir.join("old.json").exists());
+        assert!(!has_cover(&dir, "mid"));
+        assert!(has_cover(&dir, "new"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn counts_only_images_against_the_limit() {
+        let dir = temp_dir("size");
+        add_cover(&dir, "a", 100, 20);
+        add_cover(&dir, "b", 100, 10);
+        // Both sidecars are extra, and the images alone fit
+        assert_eq!(dir_size(&dir), 204);
+        evict_in(&dir, 200);
+        assert!(has_cover(&dir, "a") && has_cover(&dir, "b"));
+        evict_in(&dir, 199);
+        assert!(!has_cover(&dir, "a") && has_cover(&dir, "b"));
+        assert_eq!(dir_size(&dir), 102);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sizes_a_missing_folder_as_empty() {
+        let dir = temp_dir("missing");
+        fs::remove_dir(&dir).unwrap();
+        assert_eq!(dir_size(&dir), 0);
+    }
+}
@@ -0,0 +1,354 @@
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, Shutdown, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::PlayerState;
+use listenmoe::station::Station;
+
+const OVERLAY: &str = include_str!("../data/overlay.html");
+/// SSE clients get a comment this often, so dead connections are noticed.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+/// Give up on clients that don't send a request line in time.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Give up on clients that stop reading, so they don't hold a connection forever.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Spectrum frames go out this often on `/events` while playing.
+const SPECTRUM_INTERVAL: Duration = Duration::from_millis(50);
+/// Limits on the request line and headers, so a client can't make us buffer forever.
+const MAX_HEAD_BYTES: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 64;
+/// How often the accept loop checks whether the server was dropped.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+/// Connections served at once; more are turned away.
+const MAX_CONNECTIONS: usize = 32;
+
+/// What the endpoints report; updated from the UI thread.
+struct Shared {
+    track: Mutex<Option<TrackInfo>>,
+    station: Mutex<Station>,
+    state: Mutex<PlayerState>,
+    cover: Mutex<Option<Vec<u8>>>,
+    spectrum_bits: Arc<Vec<AtomicU32>>,
+    /// One channel per `/events` connection.
+    clients: Mutex<Vec<mpsc::Sender<String>>>,
+    /// The port as clients must name it in `Host`.
+    port: u16,
+    connections: AtomicUsize,
+}
+
+impl Shared {
+    fn now_playing(&self) -> Value {
+        json!({
+            "state": self.state.lock().unwrap().name(),
+            "station": self.station.lock().unwrap().name(),
+            "track": &*self.track.lock().unwrap(),
+        })
+    }
+
+    fn spectrum(&self) -> Value {
+        let bars: Vec<f32> = self
+            .spectrum_bits
+            .iter()
+            .map(|bits| f32::from_bits(bits.load(Ordering::Relaxed)))
+            .collect();
+        json!(bars)
+    }
+
+    /// Send an SSE event to every client, dropping the ones that went away.
+    fn broadcast(&self, event: &str, data: &Value) {
+        let message = format!("event: {event}\ndata: {data}\n\n");
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(message.clone()).is_ok());
+    }
+}
+
+/// Serves the current track, cover, spectrum and events on localhost, for
+/// browser sources in streaming software and web dashboards.
+pub struct HttpServer {
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub fn start(
+        port: u16,
+        station: Station,
+        spectrum_bits: Arc<Vec<AtomicU32>>,
+    ) -> io::Result<Self> {
+        // Only this machine can connect
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        // Polled, so the loop notices the stop flag without a connection to wake it
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            track: Mutex::new(None),
+            station: Mutex::new(station),
+            state: Mutex::new(PlayerState::Stopped),
+            cover: Mutex::new(None),
+            spectrum_bits,
+            clients: Mutex::new(Vec::new()),
+            port: addr.port(),
+            connections: AtomicUsize::new(0),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let accept = {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let mut stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        // Nothing to accept yet, or out of descriptors for now
+                        Err(_) => {
+                            thread::sleep(ACCEPT_POLL);
+                            continue;
+                        }
+                    };
+                    // Some platforms hand out accepted sockets non-blocking too
+                    if stream.set_nonblocking(false).is_err()
+                        || stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err()
+                    {
+                        continue;
+                    }
+                    if shared.connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                        shared.connections.fetch_sub(1, Ordering::Relaxed);
+                        let _ = respond(&mut stream, "503 Service Unavailable", "text/plain", b"");
+                        continue;
+                    }
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        match handle(stream, &shared) {
+                            // Clients hang up all the time, mostly on the event stream
+                            Err(err) if !is_disconnect(&err) => {
+                                warn!("HTTP client error: {err}")
+                            }
+                            _ => {}
+                        }
+                        shared.connections.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+            })
+        };
+
+        info!("Serving now playing on http://{addr}/");
+        Ok(Self {
+            shared,
+            stop,
+            accept: Some(accept),
+        })
+    }
+
+    /// Call when the UI switches to a new track (after the playback lag).
+    pub fn set_track(&self, info: &TrackInfo) {
+        *self.shared.track.lock().unwrap() = Some(info.clone());
+        self.shared.cover.lock().unwrap().take();
+        self.shared.broadcast("track", &self.shared.now_playing());
+    }
+
+    /// The artwork for the current track; clients reload `/cover` on this event.
+    pub fn set_cover(&self, bytes: &[u8]) {
+        *self.shared.cover.lock().unwrap() = Some(bytes.to_vec());
+        self.shared.broadcast("cover", &Value::Null);
+    }
+
+    pub fn set_state(&self, state: &PlayerState, station: Station) {
+        let changed = {
+            let mut current = self.shared.state.lock().unwrap();
+            let mut current_station = self.shared.station.lock().unwrap();
+            let changed = *current != *state || *current_station != station;
+            *current = state.clone();
+            *current_station = station;
+            changed
+        };
+        if changed {
+            self.shared.broadcast("state", &self.shared.now_playing());
+        }
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wait for the accept loop to see the flag and release the port, so a
+        // new server can bind it right away
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+        // Ending the channels closes the event streams
+        self.shared.clients.lock().unwrap().clear();
+    }
+}
+
+fn handle(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_HEAD_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut host = None;
+    let mut headers = 0;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return too_large(&mut stream);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_ascii_lowercase());
+            }
+        }
+        line.clear();
+    }
+    if reader.get_ref().limit() == 0 {
+        return too_large(&mut stream);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    // Web pages can point any name at 127.0.0.1; only answer to our own
+    if !host.is_some_and(|host| is_local_host(&host, shared.port)) {
+        return respond(&mut stream, "403 Forbidden", "text/plain", b"");
+    }
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+    let path = target.split('?').next().unwrap_or(target);
+    match path {
+        "/" | "/overlay" => respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            OVERLAY.as_bytes(),
+        ),
+        "/now-playing.json" => respond(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            shared.now_playing().to_string().as_bytes(),
+        ),
+        "/spectrum" => respond(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            shared.spectrum().to_string().as_bytes(),
+        ),
+        "/cover" => {
+            let cover = shared.cover.lock().unwrap().clone();
+            match cover {
+                Some(bytes) => respond(&mut stream, "200 OK", image_type(&bytes), &bytes),
+                None => respond(&mut stream, "404 Not Found", "text/plain", b""),
+            }
+        }
+        "/events" => events(stream, shared),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b""),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    stream.shutdown(Shutdown::Both)
+}
+
+fn too_large(stream: &mut TcpStream) -> io::Result<()> {
+    respond(
+        stream,
+        "431 Request Header Fields Too Large",
+        "text/plain",
+        b"",
+    )
+}
+
+fn is_local_host(host: &str, port: u16) -> bool {
+    host == format!("localhost:{port}") || host == format!("127.0.0.1:{port}")
+}
+
+/// A server-sent event stream: the current state first, then every change,
+/// and the spectrum while playing.
+fn events(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/event-stream\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: keep-alive\r\n\r\n\
+         event: state\ndata: {}\n\n",
+        shared.now_playing()
+    )?;
+    stream.flush()?;
+    shared.clients.lock().unwrap().push(tx);
+
+    let mut next_frame = Instant::now();
+    loop {
+        let playing = shared.state.lock().unwrap().is_active();
+        let wait = if playing {
+            next_frame.saturating_duration_since(Instant::now())
+        } else {
+            KEEPALIVE
+        };
+        match rx.recv_timeout(wait) {
+            Ok(message) => stream.write_all(message.as_bytes())?,
+            Err(mpsc::RecvTimeoutError::Timeout) if playing => {
+                write!(stream, "event: spectrum\ndata: {}\n\n", shared.spectrum())?;
+                next_frame = Instant::now() + SPECTRUM_INTERVAL;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => stream.write_all(b": keepalive\n\n")?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        stream.flush()?;
+    }
+    stream.shutdown(Shutdown::Both)
+}
+
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::WouldBlock
+    )
+}
+
+fn image_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.len() > 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
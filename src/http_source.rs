@@ -5,9 +5,7 @@ pub struct HttpSource {
 
 impl std::io::Read for HttpSource {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.inner
-            .read(buf)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        self.inner.read(buf).map_err(std::io::Error::other)
     }
 }
 
@@ -29,3 +27,21 @@ impl symphonia::core::io::MediaSource for HttpSource {
         None
     }
 }
+
+/// The User-Agent of every request the player makes.
+pub fn build_useragent() -> String {
+    let platform = if cfg!(target_os = "linux") {
+        "linux"
+    } else if cfg!(target_os = "windows") {
+        "windows"
+    } else {
+        "other"
+    };
+
+    format!(
+        "{}-v{}-{}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        platform
+    )
+}
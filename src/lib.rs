@@ -0,0 +1,43 @@
+//! The LISTEN.moe player without a UI: [`Player`] plays a station and reports
+//! one playback state. It drives [`Listen`], which plays the stream and
+//! measures its spectrum, and [`Meta`], which follows the gateway for the
+//! current track, in sync with what is audible. [`Api`] talks to the
+//! LISTEN.moe API.
+//!
+//! [`Meta`] is the gateway client and works on its own too. Without audio,
+//! give it a lag of zero to get each track as it starts on air:
+//!
+//! ```no_run
+//! use listenmoe::{Meta, Station};
+//! use std::sync::{atomic::AtomicU64, mpsc, Arc};
+//!
+//! let (tx, rx) = mpsc::channel();
+//! let meta = Meta::new(Station::Jpop, tx, Arc::new(AtomicU64::new(0)));
+//! meta.start();
+//! while let Ok(track) = rx.recv() {
+//!     log::info!("{} - {}", track.artist, track.title);
+//! }
+//! ```
+//!
+//! Nothing here depends on GTK; the desktop app is the `gui` feature.
+
+/// The application ID, which also names the app's folders. Debug builds get
+/// their own, so they leave the installed app's settings alone.
+#[cfg(debug_assertions)]
+pub const APP_ID: &str = "io.github.noobping.listenmoe_beta";
+#[cfg(not(debug_assertions))]
+pub const APP_ID: &str = "io.github.noobping.listenmoe";
+
+pub mod api;
+mod http_source;
+pub mod listen;
+pub mod logging;
+pub mod meta;
+pub mod player;
+pub mod station;
+
+pub use api::Api;
+pub use listen::Listen;
+pub use meta::{Meta, TrackInfo};
+pub use player::{Player, PlayerEvent, PlayerState};
+pub use station::Station;
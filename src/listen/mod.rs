@@ -1,13 +1,14 @@
+use log::error;
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
 use std::sync::atomic::AtomicU32;
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     mpsc, Arc,
 };
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::station::Station;
 
@@ -18,6 +19,8 @@ type DynError = Box<dyn Error + Send + Sync + 'static>;
 type Result<T> = std::result::Result<T, DynError>;
 
 const N_BARS: usize = 48;
+/// Points in the downsampled time-domain buffer used by the oscilloscope.
+const N_WAVE: usize = 128;
 
 #[derive(Debug, Clone, Copy)]
 enum Control {
@@ -26,6 +29,19 @@ enum Control {
     Resume,
 }
 
+/// What the stream worker is doing, in the order it happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamStatus {
+    Connecting,
+    /// Connected; waiting for the first audio.
+    Buffering,
+    Playing,
+    /// The connection dropped and the worker is trying again.
+    Reconnecting,
+    /// The worker gave up; playback has stopped.
+    Failed(String),
+}
+
 #[derive(Debug)]
 enum State {
     Stopped,
@@ -37,6 +53,9 @@ enum State {
 struct Inner {
     station: Station,
     state: State,
+    /// Reports from the current worker; dropped with it, so a stopped worker
+    /// can't report anything.
+    status: Option<mpsc::Receiver<StreamStatus>>,
 }
 
 #[derive(Debug)]
@@ -45,6 +64,8 @@ pub struct Listen {
     lag_ms: Arc<AtomicU64>,
     pause_started: RefCell<Option<Instant>>,
     spectrum_bits: Arc<Vec<AtomicU32>>,
+    waveform_bits: Arc<Vec<AtomicU32>>,
+    analysis: Arc<AtomicBool>,
 }
 
 impl Listen {
@@ -53,10 +74,13 @@ impl Listen {
             inner: RefCell::new(Inner {
                 station,
                 state: State::Stopped,
+                status: None,
             }),
             lag_ms: Arc::new(AtomicU64::new(0)),
             pause_started: RefCell::new(None),
             spectrum_bits: Arc::new((0..N_BARS).map(|_| AtomicU32::new(0)).collect()),
+            waveform_bits: Arc::new((0..N_WAVE).map(|_| AtomicU32::new(0)).collect()),
+            analysis: Arc::new(AtomicBool::new(true)),
         })
     }
 
@@ -64,10 +88,51 @@ impl Listen {
         self.spectrum_bits.clone()
     }
 
+    /// Latest mono samples (-1.0..=1.0) as f32 bits, downsampled for drawing.
+    pub fn waveform(&self) -> Arc<Vec<AtomicU32>> {
+        self.waveform_bits.clone()
+    }
+
+    /// Whether the worker computes the spectrum and waveform. Turn it off while
+    /// nothing draws them, to save the FFT.
+    pub fn set_analysis(&self, enabled: bool) {
+        self.analysis.store(enabled, Ordering::Relaxed);
+    }
+
     pub fn lag_ms(&self) -> Arc<AtomicU64> {
         self.lag_ms.clone()
     }
 
+    /// Wall-clock time of the audio that is audible right now: live time minus
+    /// the playback lag, frozen while paused.
+    pub fn playback_now(&self) -> SystemTime {
+        let mut lag = Duration::from_millis(self.lag_ms.load(Ordering::Relaxed));
+        if let Some(t0) = *self.pause_started.borrow() {
+            lag += t0.elapsed();
+        }
+        let now = SystemTime::now();
+        now.checked_sub(lag).unwrap_or(now)
+    }
+
+    /// What the worker reported since the last call, oldest first. After a
+    /// failure, playback is stopped and can be started again.
+    pub fn poll_status(&self) -> Vec<StreamStatus> {
+        let mut inner = self.inner.borrow_mut();
+        let statuses: Vec<StreamStatus> = match &inner.status {
+            Some(rx) => rx.try_iter().collect(),
+            None => return Vec::new(),
+        };
+        if statuses
+            .iter()
+            .any(|status| matches!(status, StreamStatus::Failed(_)))
+        {
+            Self::stop_inner(&mut inner);
+            self.lag_ms.store(0, Ordering::Relaxed);
+            self.pause_started.borrow_mut().take();
+        }
+        statuses
+    }
+
     pub fn get_station(&self) -> Station {
         self.inner.borrow_mut().station
     }
@@ -80,60 +145,86 @@ impl Listen {
             Self::stop_inner(&mut inner);
         }
         inner.station = station;
+        // A fresh connection is live again
+        self.lag_ms.store(0, Ordering::Relaxed);
+        self.pause_started.borrow_mut().take();
         if was_playing_or_paused {
-            Self::start_inner(&mut inner, self.spectrum_bits.clone());
+            Self::start_inner(
+                &mut inner,
+                self.spectrum_bits.clone(),
+                self.waveform_bits.clone(),
+                self.analysis.clone(),
+            );
         }
     }
 
     pub fn start(&self) {
+        let pause_started = self.pause_started.borrow_mut().take();
         if matches!(self.inner.borrow().state, State::Paused { .. }) {
-            if let Some(t0) = self.pause_started.borrow_mut().take() {
+            if let Some(t0) = pause_started {
                 let add = t0.elapsed().as_millis() as u64;
                 self.lag_ms.fetch_add(add, Ordering::Relaxed);
             }
         }
         let mut inner = self.inner.borrow_mut();
-        Self::start_inner(&mut inner, self.spectrum_bits.clone());
+        Self::start_inner(
+            &mut inner,
+            self.spectrum_bits.clone(),
+            self.waveform_bits.clone(),
+            self.analysis.clone(),
+        );
     }
 
     pub fn pause(&self) {
         let mut inner = self.inner.borrow_mut();
-        match &inner.state {
-            State::Playing { tx } => {
-                let _ = tx.send(Control::Pause);
-                inner.state = State::Paused { tx: tx.clone() };
-            }
-            _ => {}
+        if let State::Playing { tx } = &inner.state {
+            let _ = tx.send(Control::Pause);
+            inner.state = State::Paused { tx: tx.clone() };
+            *self.pause_started.borrow_mut() = Some(Instant::now());
         }
-        *self.pause_started.borrow_mut() = Some(Instant::now());
     }
 
     pub fn stop(&self) {
         let mut inner = self.inner.borrow_mut();
         Self::stop_inner(&mut inner);
+        self.lag_ms.store(0, Ordering::Relaxed);
+        self.pause_started.borrow_mut().take();
     }
 
-    fn start_inner(inner: &mut Inner, spectrum_bits: Arc<Vec<AtomicU32>>) {
+    fn start_inner(
+        inner: &mut Inner,
+        spectrum_bits: Arc<Vec<AtomicU32>>,
+        waveform_bits: Arc<Vec<AtomicU32>>,
+        analysis: Arc<AtomicBool>,
+    ) {
         match &inner.state {
             State::Playing { .. } => {
                 // already playing
-                return;
             }
             State::Paused { tx } => {
                 let _ = tx.send(Control::Resume);
                 inner.state = State::Playing { tx: tx.clone() };
-                return;
             }
             State::Stopped => {
                 let (tx, rx) = mpsc::channel::<Control>();
+                let (status_tx, status_rx) = mpsc::channel::<StreamStatus>();
                 let station = inner.station;
 
                 inner.state = State::Playing { tx: tx.clone() };
+                inner.status = Some(status_rx);
 
                 // detached worker thread; will exit on Stop or error
                 thread::spawn(move || {
-                    if let Err(err) = stream::run_listenmoe_stream(station, rx, spectrum_bits) {
-                        eprintln!("stream error: {err}");
+                    if let Err(err) = stream::run_listenmoe_stream(
+                        station,
+                        rx,
+                        status_tx.clone(),
+                        spectrum_bits,
+                        waveform_bits,
+                        analysis,
+                    ) {
+                        error!("Stream error: {err}");
+                        let _ = status_tx.send(StreamStatus::Failed(err.to_string()));
                     }
                 });
             }
@@ -145,6 +236,7 @@ impl Listen {
             let _ = tx.send(Control::Stop);
         }
         inner.state = State::Stopped;
+        inner.status = None;
     }
 }
 
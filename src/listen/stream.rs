@@ -1,6 +1,10 @@
+use log::{debug, info, warn};
 use reqwest::blocking::Client;
 use rodio::{buffer::SamplesBuffer, OutputStreamBuilder, Sink};
-use std::sync::{atomic::AtomicU32, mpsc, Arc};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    mpsc, Arc,
+};
 use std::time::Duration;
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error as SymphoniaError;
@@ -9,16 +13,14 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 
-use crate::http_source::HttpSource;
-#[cfg(debug_assertions)]
-use crate::log::now_string;
+use crate::http_source::{build_useragent, HttpSource};
 use crate::station::Station;
 
 use super::viz::{
     clear_spectrum, decode_and_process_packet, make_fft_state, reset_fft_state, DecodeState,
     FftVizState, PacketOutcome, VizParams,
 };
-use super::{Control, Result};
+use super::{Control, Result, StreamStatus};
 
 #[derive(Debug, Clone, Copy)]
 enum RunOutcome {
@@ -33,22 +35,12 @@ fn build_client() -> Result<Client> {
         .build()?)
 }
 
-fn build_useragent() -> String {
-    let platform = if cfg!(target_os = "linux") {
-        "linux"
-    } else if cfg!(target_os = "windows") {
-        "windows"
-    } else {
-        "other"
-    };
-
-    format!(
-        "{}-v{}-{}",
-        env!("CARGO_PKG_NAME"),
-        env!("CARGO_PKG_VERSION"),
-        platform
-    )
-}
+/// The demuxer, the audio track's ID and its decoder.
+type OpenedStream = (
+    Box<dyn symphonia::core::formats::FormatReader>,
+    u32,
+    Box<dyn symphonia::core::codecs::Decoder>,
+);
 
 fn open_stream(
     url: &str,
@@ -57,17 +49,11 @@ fn open_stream(
     format_opts: &FormatOptions,
     metadata_opts: &MetadataOptions,
     decoder_opts: &DecoderOptions,
-) -> Result<(
-    Box<dyn symphonia::core::formats::FormatReader>,
-    u32,
-    Box<dyn symphonia::core::codecs::Decoder>,
-)> {
-    #[cfg(debug_assertions)]
-    println!("[{}] Connecting to {url}…", now_string());
+) -> Result<OpenedStream> {
+    info!("Connecting to {url}…");
 
     let response = client.get(url).header("User-Agent", useragent).send()?;
-    #[cfg(debug_assertions)]
-    println!("[{}] HTTP status: {}", now_string(), response.status());
+    debug!(status = response.status().as_u16(); "Stream response");
 
     if !response.status().is_success() {
         return Err(format!("HTTP status {}", response.status()).into());
@@ -100,30 +86,29 @@ fn handle_control(
     paused: &mut bool,
     bars_enabled: &mut bool,
     spectrum_bits: &Arc<Vec<AtomicU32>>,
+    fft_state: &FftVizState,
 ) -> Result<bool> {
     // returns Ok(true) if Stop requested
     while let Ok(cmd) = rx.try_recv() {
         match cmd {
             Control::Stop => {
-                #[cfg(debug_assertions)]
-                println!("[{}] Stop requested, shutting down stream.", now_string());
+                debug!("Stop requested, shutting down stream.");
                 sink.stop();
                 return Ok(true);
             }
             Control::Pause => {
                 if !*paused {
-                    #[cfg(debug_assertions)]
-                    println!("[{}] Pausing playback.", now_string());
+                    debug!("Pausing playback.");
                     *paused = true;
                     sink.pause();
                 }
                 *bars_enabled = false;
                 clear_spectrum(spectrum_bits);
+                clear_spectrum(&fft_state.waveform_bits);
             }
             Control::Resume => {
                 if *paused {
-                    #[cfg(debug_assertions)]
-                    println!("[{}] Resuming playback.", now_string());
+                    debug!("Resuming playback.");
                     *paused = false;
                     sink.play();
                     *bars_enabled = true;
@@ -134,8 +119,10 @@ fn handle_control(
     Ok(false)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_one_connection(
     rx: &mpsc::Receiver<Control>,
+    status: &mpsc::Sender<StreamStatus>,
     spectrum_bits: &Arc<Vec<AtomicU32>>,
     format: &mut Box<dyn symphonia::core::formats::FormatReader>,
     track_id: &mut u32,
@@ -144,6 +131,7 @@ fn run_one_connection(
     sink: &mut Sink,
     paused: &mut bool,
     bars_enabled: &mut bool,
+    analysis: &AtomicBool,
     fft_state: &mut FftVizState,
     viz: VizParams,
 ) -> Result<RunOutcome> {
@@ -152,17 +140,17 @@ fn run_one_connection(
         channels: 0,
         sample_rate: 0,
     };
+    let mut started = false;
 
     loop {
-        if handle_control(rx, sink, paused, bars_enabled, spectrum_bits)? {
+        if handle_control(rx, sink, paused, bars_enabled, spectrum_bits, fft_state)? {
             return Ok(RunOutcome::Stop);
         }
 
         let packet = match format.next_packet() {
             Ok(p) => p,
             Err(SymphoniaError::ResetRequired) => {
-                #[cfg(debug_assertions)]
-                println!("[{}] Stream reset, reconfiguring decoder…", now_string());
+                debug!("Stream reset, reconfiguring decoder…");
 
                 let new_track = format
                     .tracks()
@@ -175,16 +163,11 @@ fn run_one_connection(
                     symphonia::default::get_codecs().make(&new_track.codec_params, decoder_opts)?;
 
                 decode_state.sample_buf = None;
-                reset_fft_state(
-                    &mut fft_state.mono_ring,
-                    &mut fft_state.bars_smooth,
-                    &mut fft_state.bar_peak,
-                    spectrum_bits,
-                );
+                reset_fft_state(fft_state, spectrum_bits);
                 continue;
             }
             Err(err) => {
-                eprintln!("Error reading packet: {err:?}");
+                warn!("Error reading packet: {err:?}");
                 return Ok(RunOutcome::Reconnect);
             }
         };
@@ -195,7 +178,7 @@ fn run_one_connection(
             track_id,
             decoder,
             decoder_opts,
-            *bars_enabled,
+            *bars_enabled && analysis.load(Ordering::Relaxed),
             spectrum_bits,
             &mut decode_state,
             fft_state,
@@ -205,19 +188,14 @@ fn run_one_connection(
         match outcome {
             PacketOutcome::Continue => {}
             PacketOutcome::Reconnect => return Ok(RunOutcome::Reconnect),
-            PacketOutcome::SpecChanged { .. } => {
+            PacketOutcome::SpecChanged => {
                 // Recreate sink on spec change
                 sink.stop();
                 if *paused {
                     sink.pause();
                 }
 
-                reset_fft_state(
-                    &mut fft_state.mono_ring,
-                    &mut fft_state.bars_smooth,
-                    &mut fft_state.bar_peak,
-                    spectrum_bits,
-                );
+                reset_fft_state(fft_state, spectrum_bits);
 
                 // Continue; next decoded buffer will create a new SampleBuffer and then deliver audio.
                 continue;
@@ -226,6 +204,10 @@ fn run_one_connection(
 
         if let Some((channels, sample_rate, samples)) = audio {
             append_samples_in_chunks(sink, channels, sample_rate, &samples); // send audio to rodio
+            if !started {
+                started = true;
+                let _ = status.send(StreamStatus::Playing);
+            }
         }
     }
 }
@@ -233,7 +215,10 @@ fn run_one_connection(
 pub(super) fn run_listenmoe_stream(
     station: Station,
     rx: mpsc::Receiver<Control>,
+    status: mpsc::Sender<StreamStatus>,
     spectrum_bits: Arc<Vec<AtomicU32>>,
+    waveform_bits: Arc<Vec<AtomicU32>>,
+    analysis: Arc<AtomicBool>,
 ) -> Result<()> {
     let primary = station.stream_url().to_string();
     let fallback = station.stream_fallback_url().to_string();
@@ -247,12 +232,12 @@ pub(super) fn run_listenmoe_stream(
     let decoder_opts: DecoderOptions = Default::default();
 
     let stream = OutputStreamBuilder::open_default_stream()?;
-    let mut sink = Sink::connect_new(&stream.mixer());
+    let mut sink = Sink::connect_new(stream.mixer());
 
     let mut paused = false;
     let mut bars_enabled = true;
 
-    let mut fft_state = make_fft_state(spectrum_bits.len());
+    let mut fft_state = make_fft_state(spectrum_bits.len(), waveform_bits);
     let viz = VizParams {
         peak_attack: 0.35,
         peak_release: 0.995,
@@ -260,6 +245,7 @@ pub(super) fn run_listenmoe_stream(
         curve: 0.75,
     };
 
+    let _ = status.send(StreamStatus::Connecting);
     loop {
         let url: &str = if use_fallback { &fallback } else { &primary };
 
@@ -273,30 +259,27 @@ pub(super) fn run_listenmoe_stream(
         ) {
             Ok(x) => x,
             Err(e) => {
-                eprintln!("connect/probe error on {url}: {e}");
+                warn!("connect/probe error on {url}: {e}");
                 if !fallback.is_empty() {
                     use_fallback = !use_fallback;
                 }
                 client = build_client()?;
+                let _ = status.send(StreamStatus::Reconnecting);
                 continue;
             }
         };
 
         // On reconnect: clear sink queue + reset viz
         sink.stop();
-        sink = Sink::connect_new(&stream.mixer());
-        reset_fft_state(
-            &mut fft_state.mono_ring,
-            &mut fft_state.bars_smooth,
-            &mut fft_state.bar_peak,
-            &spectrum_bits,
-        );
+        sink = Sink::connect_new(stream.mixer());
+        reset_fft_state(&mut fft_state, &spectrum_bits);
+        let _ = status.send(StreamStatus::Buffering);
 
-        #[cfg(debug_assertions)]
-        println!("[{}] Started decoding + playback.", now_string());
+        debug!("Started decoding + playback.");
 
         let outcome = run_one_connection(
             &rx,
+            &status,
             &spectrum_bits,
             &mut format,
             &mut track_id,
@@ -305,6 +288,7 @@ pub(super) fn run_listenmoe_stream(
             &mut sink,
             &mut paused,
             &mut bars_enabled,
+            &analysis,
             &mut fft_state,
             viz,
         )?;
@@ -315,6 +299,7 @@ pub(super) fn run_listenmoe_stream(
                 if !fallback.is_empty() {
                     use_fallback = !use_fallback;
                 }
+                let _ = status.send(StreamStatus::Reconnecting);
                 continue;
             }
         }
@@ -1,3 +1,4 @@
+use log::{debug, warn};
 use rustfft::{num_complex::Complex32, FftPlanner};
 use std::sync::{
     atomic::{AtomicU32, Ordering},
@@ -7,9 +8,6 @@ use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error as SymphoniaError;
 
-#[cfg(debug_assertions)]
-use crate::log::now_string;
-
 use super::Result;
 
 const FFT_SIZE: usize = 1024;
@@ -24,6 +22,7 @@ pub(super) struct FftVizState {
     pub(super) bar_peak: Vec<f32>,
     pub(super) window: Vec<f32>,
     pub(super) fft: std::sync::Arc<dyn rustfft::Fft<f32>>,
+    pub(super) waveform_bits: Arc<Vec<AtomicU32>>,
 }
 
 pub(super) struct DecodeState {
@@ -47,7 +46,10 @@ pub(super) enum PacketOutcome {
     SpecChanged,
 }
 
-pub(super) fn make_fft_state(num_bars: usize) -> FftVizState {
+/// Channels, sample rate and the interleaved samples of a decoded packet.
+pub(super) type Samples = (u16, u32, Vec<f32>);
+
+pub(super) fn make_fft_state(num_bars: usize, waveform_bits: Arc<Vec<AtomicU32>>) -> FftVizState {
     let mut planner = FftPlanner::<f32>::new();
     let fft = planner.plan_fft_forward(FFT_SIZE);
 
@@ -60,6 +62,7 @@ pub(super) fn make_fft_state(num_bars: usize) -> FftVizState {
         bar_peak: vec![0.0; num_bars],
         window: hann_window(FFT_SIZE),
         fft,
+        waveform_bits,
     }
 }
 
@@ -67,6 +70,7 @@ pub(super) fn make_fft_state(num_bars: usize) -> FftVizState {
 /// - PacketOutcome::Continue + Some((ch, sr, samples)) when audio is ready
 /// - PacketOutcome::SpecChanged when SR/ch changed (caller should recreate sink + reset FFT)
 /// - PacketOutcome::Reconnect on fatal decode
+#[allow(clippy::too_many_arguments)]
 pub(super) fn decode_and_process_packet(
     packet: &symphonia::core::formats::Packet,
     format: &mut Box<dyn symphonia::core::formats::FormatReader>,
@@ -78,7 +82,7 @@ pub(super) fn decode_and_process_packet(
     decode_state: &mut DecodeState,
     fft_state: &mut FftVizState,
     viz: VizParams,
-) -> Result<(PacketOutcome, Option<(u16, u32, Vec<f32>)>)> {
+) -> Result<(PacketOutcome, Option<Samples>)> {
     if packet.track_id() != *track_id {
         return Ok((PacketOutcome::Continue, None));
     }
@@ -87,11 +91,7 @@ pub(super) fn decode_and_process_packet(
         Ok(buf) => buf,
         Err(SymphoniaError::DecodeError(_)) => return Ok((PacketOutcome::Continue, None)),
         Err(SymphoniaError::ResetRequired) => {
-            #[cfg(debug_assertions)]
-            println!(
-                "[{}] Decoder reset required, rebuilding decoder…",
-                now_string()
-            );
+            debug!("Decoder reset required, rebuilding decoder…");
 
             let new_track = format
                 .tracks()
@@ -104,16 +104,11 @@ pub(super) fn decode_and_process_packet(
                 symphonia::default::get_codecs().make(&new_track.codec_params, decoder_opts)?;
 
             decode_state.sample_buf = None;
-            reset_fft_state(
-                &mut fft_state.mono_ring,
-                &mut fft_state.bars_smooth,
-                &mut fft_state.bar_peak,
-                spectrum_bits,
-            );
+            reset_fft_state(fft_state, spectrum_bits);
             return Ok((PacketOutcome::Continue, None));
         }
         Err(err) => {
-            eprintln!("Fatal decode error: {err:?}");
+            warn!("Fatal decode error: {err:?}");
             return Ok((PacketOutcome::Reconnect, None));
         }
     };
@@ -146,10 +141,20 @@ pub(super) fn decode_and_process_packet(
     buf.copy_interleaved_ref(decoded);
     let samples = buf.samples().to_owned();
 
+    if !bars_enabled {
+        // Nothing is drawn, so skip the analysis entirely
+        fft_state.mono_ring.clear();
+        clear_spectrum(spectrum_bits);
+        clear_spectrum(&fft_state.waveform_bits);
+        return Ok((
+            PacketOutcome::Continue,
+            Some((decode_state.channels, decode_state.sample_rate, samples)),
+        ));
+    }
+
     // Downmix to mono ring buffer for FFT
     let ch = decode_state.channels as usize;
-    if ch > 0 {
-        let frames = samples.len() / ch;
+    if let Some(frames) = samples.len().checked_div(ch) {
         fft_state.mono_ring.reserve(frames);
 
         for f in 0..frames {
@@ -163,6 +168,8 @@ pub(super) fn decode_and_process_packet(
 
     // FFT + bars
     while fft_state.mono_ring.len() >= FFT_SIZE {
+        publish_waveform(&fft_state.mono_ring[..FFT_SIZE], &fft_state.waveform_bits);
+
         for i in 0..FFT_SIZE {
             let x = fft_state.mono_ring[i] * fft_state.window[i];
             fft_state.fft_in[i] = Complex32::new(x, 0.0);
@@ -209,12 +216,8 @@ pub(super) fn decode_and_process_packet(
             fft_state.bars_smooth[i] = fft_state.bars_smooth[i] * 0.80 + fft_state.bars[i] * 0.20;
         }
 
-        if bars_enabled {
-            for (i, v) in fft_state.bars_smooth.iter().enumerate() {
-                spectrum_bits[i].store(v.to_bits(), Ordering::Relaxed);
-            }
-        } else {
-            clear_spectrum(spectrum_bits);
+        for (i, v) in fft_state.bars_smooth.iter().enumerate() {
+            spectrum_bits[i].store(v.to_bits(), Ordering::Relaxed);
         }
 
         let hop = HOP.min(fft_state.mono_ring.len());
@@ -227,16 +230,22 @@ pub(super) fn decode_and_process_packet(
     ))
 }
 
-pub(super) fn reset_fft_state(
-    mono_ring: &mut Vec<f32>,
-    bars_smooth: &mut [f32],
-    bar_peak: &mut [f32],
-    spectrum_bits: &Arc<Vec<AtomicU32>>,
-) {
-    mono_ring.clear();
-    bars_smooth.fill(0.0);
-    bar_peak.fill(0.0);
+pub(super) fn reset_fft_state(fft_state: &mut FftVizState, spectrum_bits: &Arc<Vec<AtomicU32>>) {
+    fft_state.mono_ring.clear();
+    fft_state.bars_smooth.fill(0.0);
+    fft_state.bar_peak.fill(0.0);
     clear_spectrum(spectrum_bits);
+    clear_spectrum(&fft_state.waveform_bits);
+}
+
+/// Average `samples` down to one value per slot of `waveform_bits`.
+fn publish_waveform(samples: &[f32], waveform_bits: &Arc<Vec<AtomicU32>>) {
+    let n = waveform_bits.len().max(1);
+    let step = (samples.len() / n).max(1);
+    for (slot, chunk) in waveform_bits.iter().zip(samples.chunks(step)) {
+        let avg = chunk.iter().sum::<f32>() / chunk.len() as f32;
+        slot.store(avg.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
 }
 
 fn hann_window(n: usize) -> Vec<f32> {
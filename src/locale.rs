@@ -2,9 +2,13 @@ use dirs_next as dirs;
 use gettextrs::{
     bind_textdomain_codeset, bindtextdomain, setlocale, textdomain, LocaleCategory,
 };
+use log::debug;
 use std::{env, path::{Path, PathBuf}};
 
-const APP_ID: &str = "io.github.noobping.listenmoe";
+use listenmoe::APP_ID;
+
+/// The packages name the .mo files after the release ID, debug builds included.
+const TEXT_DOMAIN: &str = "io.github.noobping.listenmoe";
 
 fn find_locale_dir() -> PathBuf {
     // Developer directory (cargo run)
@@ -59,14 +63,13 @@ pub fn init_i18n() {
     setlocale(LocaleCategory::LcAll, "");
 
     let dir = find_locale_dir();
-    #[cfg(debug_assertions)]
-    println!("Using locale dir: {}", dir.display());
+    debug!("Using locale dir: {}", dir.display());
 
     let dir_str = dir
         .to_str()
         .expect("Locale path must be UTF-8 for gettext");
 
-    bindtextdomain(APP_ID, dir_str).expect("bindtextdomain failed");
-    bind_textdomain_codeset(APP_ID, "UTF-8").expect("bind codeset failed");
-    textdomain(APP_ID).expect("textdomain failed");
+    bindtextdomain(TEXT_DOMAIN, dir_str).expect("bindtextdomain failed");
+    bind_textdomain_codeset(TEXT_DOMAIN, "UTF-8").expect("bind codeset failed");
+    textdomain(TEXT_DOMAIN).expect("textdomain failed");
 }
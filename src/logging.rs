@@ -0,0 +1,242 @@
+use dirs_next as dirs;
+use log::kv::{Error, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    collections::VecDeque,
+    env,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+};
+use time::OffsetDateTime;
+
+use crate::APP_ID;
+
+/// Overrides the terminal level: `error`, `warn`, `info`, `debug` or `trace`.
+pub const ENV_VAR: &str = "LISTENMOE_LOG";
+const FILE_NAME: &str = "listenmoe.log";
+/// Start a new file from this size on, keeping a few old ones.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const KEEP_FILES: usize = 3;
+/// Lines of this session kept in memory for [`recent`].
+const RECENT_LINES: usize = 5000;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Writes to stderr at the chosen level, and at debug level to memory and,
+/// when enabled, to a file. Other crates only get through with warnings.
+struct Logger {
+    /// A [`LevelFilter`] as `usize`, raised by [`set_verbose`].
+    stderr_level: AtomicUsize,
+    /// Set from the environment, which wins over [`set_verbose`].
+    level_from_env: bool,
+    file: Mutex<Option<LogFile>>,
+    recent: Mutex<VecDeque<String>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        (metadata.level() <= Level::Warn || metadata.target().starts_with("listenmoe"))
+            && metadata.level() <= self.stderr_level().max(LevelFilter::Debug)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = format!(
+            "{} {:<5} {}: {}",
+            timestamp(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let _ = record.key_values().visit(&mut Fields(&mut line));
+
+        if record.level() <= self.stderr_level() {
+            eprintln!("{line}");
+        }
+        if record.level() > Level::Debug {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(Err(err)) = file.as_mut().map(|f| f.write(&line)) {
+                eprintln!("Stopped writing the log file: {err}");
+                file.take();
+            }
+        }
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+impl Logger {
+    fn stderr_level(&self) -> LevelFilter {
+        LevelFilter::iter()
+            .nth(self.stderr_level.load(Ordering::Relaxed))
+            .unwrap_or(LevelFilter::Warn)
+    }
+}
+
+/// Appends `key=value` for every structured field of a record.
+struct Fields<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let _ = write!(self.0, " {key}={value}");
+        Ok(())
+    }
+}
+
+/// The current file, moved aside to `.1`, `.2`, … once it grows too big.
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size >= MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..KEEP_FILES).rev() {
+            let _ = fs::rename(rotated(&self.path, n), rotated(&self.path, n + 1));
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    path.with_extension(format!("log.{n}"))
+}
+
+/// Install the logger. Warnings and errors reach the terminal, everything from
+/// debug builds; the environment variable overrides that.
+pub fn init() {
+    let default = if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Warn
+    };
+    let from_env = env::var(ENV_VAR).ok().and_then(|level| level.parse().ok());
+    let stderr_level = from_env.unwrap_or(default);
+    let logger = LOGGER.get_or_init(|| Logger {
+        stderr_level: AtomicUsize::new(stderr_level as usize),
+        level_from_env: from_env.is_some(),
+        file: Mutex::new(None),
+        recent: Mutex::new(VecDeque::new()),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(stderr_level.max(LevelFilter::Debug));
+    }
+}
+
+/// Also log debug messages to the terminal, unless the environment variable
+/// says otherwise.
+pub fn set_verbose(verbose: bool) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    if verbose && !logger.level_from_env {
+        let level = logger.stderr_level().max(LevelFilter::Debug);
+        logger.stderr_level.store(level as usize, Ordering::Relaxed);
+    }
+}
+
+/// Start or stop writing the log file in [`log_dir`].
+pub fn set_file_enabled(enabled: bool) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let file = match log_dir() {
+        Some(dir) if enabled => match LogFile::open(&dir) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                log::error!("Failed to open the log file in {}: {err}", dir.display());
+                None
+            }
+        },
+        _ => None,
+    };
+    if let Ok(mut current) = logger.file.lock() {
+        *current = file;
+    }
+}
+
+/// `logs` in the XDG state dir, or in the local data dir where there is none.
+pub fn log_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(target_os = "linux") => dirs::home_dir()?.join(".local").join("state"),
+        None => dirs::data_local_dir()?,
+    };
+    Some(base.join(APP_ID).join("logs"))
+}
+
+/// Everything logged this session at debug level, oldest first, for bug reports.
+pub fn recent() -> String {
+    let Some(logger) = LOGGER.get() else {
+        return String::new();
+    };
+    let Ok(recent) = logger.recent.lock() else {
+        return String::new();
+    };
+    recent.iter().fold(String::new(), |mut text, line| {
+        text.push_str(line);
+        text.push('\n');
+        text
+    })
+}
+
+/// UTC, with milliseconds.
+fn timestamp() -> String {
+    let now = OffsetDateTime::now_utc();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.millisecond()
+    )
+}
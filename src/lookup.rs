@@ -0,0 +1,200 @@
+use dirs_next as dirs;
+use std::{fs, io, path::PathBuf};
+
+use listenmoe::meta::TrackInfo;
+use listenmoe::APP_ID;
+
+const FILE_NAME: &str = "lookup.txt";
+
+const DEFAULT_LINKS: &[(&str, &str)] = &[
+    ("LISTEN.moe song", "https://listen.moe/songs/{song_id}"),
+    ("LISTEN.moe artist", "https://listen.moe/artists/{artist_id}"),
+    ("LISTEN.moe album", "https://listen.moe/albums/{album_id}"),
+    (
+        "MusicBrainz",
+        "https://musicbrainz.org/taglookup?tag-lookup.artist={artist}&tag-lookup.track={title}",
+    ),
+    (
+        "YouTube",
+        "https://www.youtube.com/results?search_query={artist}%20{title}",
+    ),
+    ("Web search", "https://duckduckgo.com/?q={artist}%20{title}"),
+];
+
+/// A labelled URL template for looking up the current track elsewhere.
+/// Placeholders: `{artist}`, `{title}`, `{album}`, `{song_id}`, `{artist_id}`, `{album_id}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupLink {
+    pub label: String,
+    pub template: String,
+}
+
+impl LookupLink {
+    /// Fill in the placeholders, URL-encoding each value. Returns `None` when the
+    /// template needs a value this track does not have.
+    pub fn expand(&self, info: &TrackInfo) -> Option<String> {
+        let mut out = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let key = &rest[start + 1..start + len];
+            let value = match key {
+                "artist" => Some(info.artist.clone()),
+                "title" => Some(info.title.clone()),
+                "album" => info.album.clone(),
+                "song_id" => info.song_id.map(|id| id.to_string()),
+                "artist_id" => info.artist_id.map(|id| id.to_string()),
+                "album_id" => info.album_id.map(|id| id.to_string()),
+                _ => {
+                    // Unknown placeholder: keep it literally.
+                    out.push_str(&rest[start..=start + len]);
+                    rest = &rest[start + len + 1..];
+                    continue;
+                }
+            };
+            out.push_str(&percent_encode(&value.filter(|v| !v.is_empty())?));
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        Some(out)
+    }
+}
+
+pub fn default_links() -> Vec<LookupLink> {
+    DEFAULT_LINKS
+        .iter()
+        .map(|(label, template)| LookupLink {
+            label: label.to_string(),
+            template: template.to_string(),
+        })
+        .collect()
+}
+
+/// Links from the user's `lookup.txt`, or the defaults when there is none.
+pub fn load_links() -> Vec<LookupLink> {
+    match config_path().map(fs::read_to_string) {
+        Some(Ok(text)) => parse_links(&text),
+        _ => default_links(),
+    }
+}
+
+pub fn save_links(links: &[LookupLink]) -> io::Result<()> {
+    let path = config_path().ok_or_else(|| io::Error::other("no config dir"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, format_links(links))
+}
+
+/// One `Label = template` per line; empty lines and `#` comments are ignored.
+pub fn parse_links(text: &str) -> Vec<LookupLink> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let (label, template) = l.split_once('=')?;
+            let (label, template) = (label.trim(), template.trim());
+            (!label.is_empty() && !template.is_empty()).then(|| LookupLink {
+                label: label.to_owned(),
+                template: template.to_owned(),
+            })
+        })
+        .collect()
+}
+
+pub fn format_links(links: &[LookupLink]) -> String {
+    links
+        .iter()
+        .map(|l| format!("{} = {}\n", l.label, l.template))
+        .collect()
+}
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|base| base.join(APP_ID).join(FILE_NAME))
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() * 3);
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> TrackInfo {
+        TrackInfo {
+            song_id: Some(42),
+            artist: "YOASOBI".into(),
+            artists: vec!["YOASOBI".into()],
+            title: "アイドル".into(),
+            album: Some(String::new()),
+            ..TrackInfo::default()
+        }
+    }
+
+    #[test]
+    fn keeps_unreserved_characters() {
+        assert_eq!(percent_encode("AZaz09-._~"), "AZaz09-._~");
+    }
+
+    #[test]
+    fn encodes_everything_else_per_byte() {
+        assert_eq!(percent_encode("a b&c=d/e?"), "a%20b%26c%3Dd%2Fe%3F");
+        assert_eq!(percent_encode("100%"), "100%25");
+        assert_eq!(percent_encode("é"), "%C3%A9");
+        assert_eq!(percent_encode("アイ"), "%E3%82%A2%E3%82%A4");
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let link = LookupLink {
+            label: "Search".into(),
+            template: "https://example.com/?q={artist}%20{title}&id={song_id}".into(),
+        };
+        assert_eq!(
+            link.expand(&track()).as_deref(),
+            Some("https://example.com/?q=YOASOBI%20%E3%82%A2%E3%82%A4%E3%83%89%E3%83%AB&id=42")
+        );
+    }
+
+    #[test]
+    fn skips_links_for_missing_values() {
+        let link = |template: &str| LookupLink {
+            label: "Link".into(),
+            template: template.into(),
+        };
+        assert_eq!(
+            link("https://example.com/{artist_id}").expand(&track()),
+            None
+        );
+        // An empty value is as good as none
+        assert_eq!(link("https://example.com/{album}").expand(&track()), None);
+        assert_eq!(
+            link("https://example.com/{unknown}")
+                .expand(&track())
+                .as_deref(),
+            Some("https://example.com/{unknown}")
+        );
+    }
+
+    #[test]
+    fn round_trips_the_links_file() {
+        let links = default_links();
+        assert_eq!(parse_links(&format_links(&links)), links);
+        assert!(parse_links("# comment\n\nno separator\n = empty label\n").is_empty());
+    }
+}
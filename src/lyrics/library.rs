@@ -0,0 +1,133 @@
+use dirs_next as dirs;
+use log::debug;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::lrc::Lyrics;
+use super::matcher::{artist_similarity, title_similarity};
+use listenmoe::APP_ID;
+
+const MAX_DEPTH: usize = 3;
+const MIN_TITLE_SCORE: f32 = 0.7;
+const MIN_SCORE: f32 = 0.6;
+
+/// `$LISTENMOE_LYRICS_DIR`, or `lyrics` in the user data dir.
+pub fn default_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("LISTENMOE_LYRICS_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    dirs::data_dir().map(|base| base.join(APP_ID).join("lyrics"))
+}
+
+/// The `.lrc` files in a folder, by the artist and title they are for. Build
+/// it once and scan again when the folder changes; only the file that
+/// matches a track is read again.
+#[derive(Debug, Clone)]
+pub struct LyricsLibrary {
+    dir: PathBuf,
+    dirs: Vec<PathBuf>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    path: PathBuf,
+    artist: Option<String>,
+    title: String,
+}
+
+impl LyricsLibrary {
+    /// Read the tags of every `.lrc` file in `dir` and its subfolders.
+    /// Files are matched on their `[ar:]`/`[ti:]` tags, or on an
+    /// "Artist - Title.lrc" file name when the tags are missing.
+    pub fn scan(dir: &Path) -> Self {
+        let mut files = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        collect_lrc_files(dir, 0, &mut files, &mut dirs);
+
+        let entries: Vec<Entry> = files
+            .into_iter()
+            .filter_map(|path| {
+                let lyrics = Lyrics::parse(&fs::read_to_string(&path).ok()?);
+                if lyrics.lines.is_empty() {
+                    return None;
+                }
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let (name_artist, name_title) = match stem.split_once(" - ") {
+                    Some((a, t)) => (Some(a.to_owned()), t.to_owned()),
+                    None => (None, stem),
+                };
+                Some(Entry {
+                    artist: lyrics.artist.or(name_artist),
+                    title: lyrics.title.unwrap_or(name_title),
+                    path,
+                })
+            })
+            .collect();
+        debug!(files = entries.len(); "Scanned lyrics in {}", dir.display());
+        Self {
+            dir: dir.to_path_buf(),
+            dirs,
+            entries,
+        }
+    }
+
+    /// The folder this was built from.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The folder and every subfolder that was scanned, to watch for changes.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// The lyrics that best match the artist and title.
+    pub fn find(&self, artist: &str, title: &str) -> Option<Lyrics> {
+        let mut best: Option<(f32, &Entry)> = None;
+        for entry in &self.entries {
+            let t_score = title_similarity(title, &entry.title);
+            if t_score < MIN_TITLE_SCORE {
+                continue;
+            }
+            let score = match &entry.artist {
+                Some(a) => 0.65 * t_score + 0.35 * artist_similarity(artist, a),
+                None => 0.9 * t_score,
+            };
+            if score >= MIN_SCORE && best.is_none_or(|(s, _)| score > s) {
+                best = Some((score, entry));
+            }
+        }
+
+        let (score, entry) = best?;
+        debug!("Lyrics match for {artist} - {title}: score {score:.2}");
+        // The file may have changed since the scan
+        let lyrics = Lyrics::parse(&fs::read_to_string(&entry.path).ok()?);
+        (!lyrics.lines.is_empty()).then_some(lyrics)
+    }
+}
+
+fn collect_lrc_files(dir: &Path, depth: usize, out: &mut Vec<PathBuf>, dirs: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                collect_lrc_files(&path, depth + 1, out, dirs);
+                dirs.push(path);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("lrc"))
+        {
+            out.push(path);
+        }
+    }
+}
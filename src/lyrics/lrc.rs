@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+/// A single timed line.
+#[derive(Debug, Clone)]
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+/// Parsed LRC file. `[offset:]` is already applied to the line times.
+#[derive(Debug, Clone, Default)]
+pub struct Lyrics {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    pub fn parse(src: &str) -> Self {
+        let mut lyrics = Lyrics::default();
+        let mut offset_ms: i64 = 0;
+        let mut raw: Vec<(i64, String)> = Vec::new();
+
+        for line in src.lines() {
+            let mut rest = line.trim();
+            let mut times: Vec<i64> = Vec::new();
+
+            // A line can carry several leading tags: `[00:12.00][01:30.50]text`
+            while let Some(body) = rest.strip_prefix('[') {
+                let Some(end) = body.find(']') else { break };
+                let tag = &body[..end];
+                rest = &body[end + 1..];
+
+                if let Some(ms) = parse_timestamp(tag) {
+                    times.push(ms);
+                } else if let Some((key, value)) = tag.split_once(':') {
+                    let value = value.trim();
+                    match key.trim().to_ascii_lowercase().as_str() {
+                        "offset" => offset_ms = value.parse().unwrap_or(0),
+                        "ar" if !value.is_empty() => lyrics.artist = Some(value.to_owned()),
+                        "ti" if !value.is_empty() => lyrics.title = Some(value.to_owned()),
+                        _ => {}
+                    }
+                }
+            }
+
+            if times.is_empty() {
+                continue;
+            }
+            let text = strip_word_timestamps(rest).trim().to_owned();
+            for t in times {
+                raw.push((t, text.clone()));
+            }
+        }
+
+        // A positive offset makes the lyrics appear sooner.
+        lyrics.lines = raw
+            .into_iter()
+            .map(|(t, text)| LyricLine {
+                time: Duration::from_millis((t - offset_ms).max(0) as u64),
+                text,
+            })
+            .collect();
+        lyrics.lines.sort_by_key(|l| l.time);
+        lyrics
+    }
+
+    /// Index of the line that should be shown at `pos` into the song.
+    pub fn line_at(&self, pos: Duration) -> Option<usize> {
+        match self.lines.partition_point(|l| l.time <= pos) {
+            0 => None,
+            n => Some(n - 1),
+        }
+    }
+}
+
+/// `mm:ss`, `mm:ss.xx`, `mm:ss.xxx` or `mm:ss:xx` to milliseconds.
+fn parse_timestamp(tag: &str) -> Option<i64> {
+    let (min, rest) = tag.split_once(':')?;
+    let min: i64 = min.trim().parse().ok()?;
+    let (sec, frac) = match rest.find(['.', ':']) {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, ""),
+    };
+    let sec: i64 = sec.trim().parse().ok()?;
+    let frac_ms = if frac.is_empty() {
+        0
+    } else {
+        let digits: String = frac.chars().take(3).collect();
+        let value: i64 = digits.parse().ok()?;
+        match digits.len() {
+            1 => value * 100,
+            2 => value * 10,
+            _ => value,
+        }
+    };
+    Some(min * 60_000 + sec * 1000 + frac_ms)
+}
+
+/// Enhanced LRC puts per-word times in angle brackets; drop them.
+fn strip_word_timestamps(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(lyrics: &Lyrics) -> Vec<u64> {
+        lyrics
+            .lines
+            .iter()
+            .map(|l| l.time.as_millis() as u64)
+            .collect()
+    }
+
+    #[test]
+    fn parses_timestamp_formats() {
+        assert_eq!(parse_timestamp("01:02.34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:02:34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:02.345"), Some(62_345));
+        assert_eq!(parse_timestamp("01:02.3"), Some(62_300));
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("ar:Someone"), None);
+    }
+
+    #[test]
+    fn reads_tags_and_lines() {
+        let lyrics =
+            Lyrics::parse("[ar: LiSA ]\n[ti:Gurenge]\n[00:01.00]first\n[00:02:50]second\n");
+        assert_eq!(lyrics.artist.as_deref(), Some("LiSA"));
+        assert_eq!(lyrics.title.as_deref(), Some("Gurenge"));
+        assert_eq!(times(&lyrics), [1_000, 2_500]);
+        assert_eq!(lyrics.lines[1].text, "second");
+    }
+
+    #[test]
+    fn repeats_lines_with_several_timestamps() {
+        let lyrics = Lyrics::parse("[00:10.00][00:30.00]chorus\n[00:20.00]verse\n");
+        assert_eq!(times(&lyrics), [10_000, 20_000, 30_000]);
+        let texts: Vec<&str> = lyrics.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["chorus", "verse", "chorus"]);
+    }
+
+    #[test]
+    fn sorts_out_of_order_lines() {
+        let lyrics = Lyrics::parse("[00:03.00]c\n[00:01.00]a\n[00:02.00]b\n");
+        assert_eq!(times(&lyrics), [1_000, 2_000, 3_000]);
+        assert_eq!(lyrics.lines[0].text, "a");
+    }
+
+    #[test]
+    fn applies_offset() {
+        // Positive is sooner, and nothing goes below zero
+        let lyrics = Lyrics::parse("[offset:500]\n[00:00.20]a\n[00:02.00]b\n");
+        assert_eq!(times(&lyrics), [0, 1_500]);
+        let lyrics = Lyrics::parse("[offset:-500]\n[00:02.00]b\n");
+        assert_eq!(times(&lyrics), [2_500]);
+    }
+
+    #[test]
+    fn strips_word_timestamps() {
+        let lyrics = Lyrics::parse("[00:01.00]<00:01.00>one <00:01.50>two <3\n");
+        assert_eq!(lyrics.lines[0].text, "one two <3");
+    }
+
+    #[test]
+    fn finds_line_at_position() {
+        let lyrics = Lyrics::parse("[00:01.00]a\n[00:02.00]b\n");
+        assert_eq!(lyrics.line_at(Duration::from_millis(500)), None);
+        assert_eq!(lyrics.line_at(Duration::from_millis(1_000)), Some(0));
+        assert_eq!(lyrics.line_at(Duration::from_millis(5_000)), Some(1));
+    }
+}
@@ -0,0 +1,127 @@
+/// Fold a name into a form where spelling variants compare equal: full-width
+/// ASCII becomes half-width, katakana becomes hiragana, case and punctuation
+/// are dropped.
+pub(super) fn normalize(s: &str) -> String {
+    s.chars()
+        .map(fold_char)
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Same as [`normalize`], but with bracketed parts like "(TV size)" removed.
+pub(super) fn normalize_stripped(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut depth = 0usize;
+    for c in s.chars().map(fold_char) {
+        match c {
+            '(' | '[' | '{' | '「' | '『' | '【' => depth += 1,
+            ')' | ']' | '}' | '」' | '』' | '】' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    normalize(&out)
+}
+
+fn fold_char(c: char) -> char {
+    match c as u32 {
+        // Full-width ASCII variants
+        0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        // Ideographic space
+        0x3000 => ' ',
+        // Katakana to hiragana (ァ..ヶ)
+        0x30A1..=0x30F6 => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Similarity of two normalized strings in `0.0..=1.0`.
+pub(super) fn similarity(a: &str, b: &str) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (short, long) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+
+    // Containment covers "Title" vs "Title TV size" and artist lists.
+    if long.windows(short.len()).any(|w| w == short.as_slice()) {
+        return 0.85 + 0.15 * (short.len() as f32 / long.len() as f32);
+    }
+    if short.len() < 2 {
+        return 0.0;
+    }
+
+    // Dice coefficient over character bigrams; works for kana and kanji as well.
+    let mut bigrams: Vec<(char, char)> = a.windows(2).map(|w| (w[0], w[1])).collect();
+    let total = bigrams.len() + b.len() - 1;
+    let mut shared = 0usize;
+    for w in b.windows(2) {
+        if let Some(i) = bigrams.iter().position(|&p| p == (w[0], w[1])) {
+            bigrams.swap_remove(i);
+            shared += 1;
+        }
+    }
+    (2 * shared) as f32 / total as f32
+}
+
+/// Best similarity of `candidate` against any of the (comma separated) artists.
+pub(super) fn artist_similarity(artists: &str, candidate: &str) -> f32 {
+    let candidate = normalize(candidate);
+    std::iter::once(artists)
+        .chain(artists.split([',', '、', '&']))
+        .map(|a| similarity(&normalize(a), &candidate))
+        .fold(0.0, f32::max)
+}
+
+pub(super) fn title_similarity(title: &str, candidate: &str) -> f32 {
+    let full = similarity(&normalize(title), &normalize(candidate));
+    let stripped = similarity(&normalize_stripped(title), &normalize_stripped(candidate));
+    full.max(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_case_punctuation_and_width() {
+        assert_eq!(normalize("Hello, World!"), "helloworld");
+        assert_eq!(normalize("ＡＢＣ　１２３"), "abc123");
+        assert_eq!(normalize("カタカナ"), normalize("かたかな"));
+    }
+
+    #[test]
+    fn strips_bracketed_parts() {
+        assert_eq!(normalize_stripped("Gurenge (TV size)"), "gurenge");
+        assert_eq!(normalize_stripped("曲名【Off Vocal】"), "曲名");
+    }
+
+    #[test]
+    fn matches_titles_despite_spelling() {
+        assert_eq!(title_similarity("GURENGE", "gurenge"), 1.0);
+        assert_eq!(title_similarity("Song (feat. Someone)", "Song"), 1.0);
+        assert!(title_similarity("Gurenge", "Homura") < 0.5);
+    }
+
+    #[test]
+    fn matches_any_listed_artist() {
+        assert_eq!(artist_similarity("LiSA, Uru", "Uru"), 1.0);
+        assert_eq!(artist_similarity("LiSA & Uru", "lisa"), 1.0);
+        assert!(artist_similarity("LiSA feat. Uru", "LiSA") > 0.85);
+        assert!(artist_similarity("LiSA", "Aimer") < 0.5);
+    }
+
+    #[test]
+    fn similarity_is_bounded() {
+        assert_eq!(similarity("", "abc"), 0.0);
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        let score = similarity("abcdef", "abcxyz");
+        assert!(score > 0.0 && score < 1.0);
+    }
+}
@@ -0,0 +1,6 @@
+mod library;
+mod lrc;
+mod matcher;
+
+pub use library::{default_dir, LyricsLibrary};
+pub use lrc::Lyrics;
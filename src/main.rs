@@ -1,26 +1,27 @@
 #![cfg_attr(all(not(debug_assertions), target_os = "windows"), windows_subsystem = "windows")]
 
-mod http_source;
-mod listen;
+mod atomic_file;
+mod cover_cache;
+mod http_server;
 mod locale;
-#[cfg(debug_assertions)]
-mod log;
-mod meta;
-mod station;
+mod lookup;
+mod lyrics;
+mod now_playing;
+mod settings;
 mod ui;
 
-#[cfg(debug_assertions)]
-const APP_ID: &str = "io.github.noobping.listenmoe_beta";
-#[cfg(not(debug_assertions))]
-const APP_ID: &str = "io.github.noobping.listenmoe";
 #[cfg(target_os = "windows")]
 const RESOURCE_ID: &str = "/io/github/noobping/listenmoe";
 #[cfg(target_os = "windows")]
 use adw::gtk::{gdk::Display, IconTheme};
+use adw::gtk::gio::ApplicationFlags;
 use adw::prelude::*;
 use adw::Application;
+use listenmoe::APP_ID;
 
 fn main() {
+    // First, so everything after this can log. `--verbose` is applied once GLib parsed it.
+    listenmoe::logging::init();
     locale::init_i18n();
 
     // Register resources compiled into the binary. If this fails, the app cannot find its assets.
@@ -39,7 +40,14 @@ fn main() {
     }
 
     // Create the GTK application. The application ID must be unique and corresponds to the desktop file name.
-    let app = Application::builder().application_id(APP_ID).build();
+    let app = Application::builder()
+        .application_id(APP_ID)
+        .flags(ApplicationFlags::HANDLES_COMMAND_LINE)
+        .build();
+    ui::add_options(&app);
+    app.connect_handle_local_options(ui::handle_local_options);
+    // Options from a second launch are forwarded to the running instance.
+    app.connect_command_line(ui::command_line);
     app.connect_activate(ui::build_ui); // Build the UI when the application is activated.
     app.run(); // Run the application. This function does not return until the last window is closed.
 }
@@ -1,7 +1,8 @@
+use log::error;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::mpsc;
-use std::sync::{atomic::AtomicU64, Arc};
+use std::sync::{atomic::AtomicU64, Arc, Mutex};
 use std::thread;
 
 use crate::station::Station;
@@ -29,8 +30,11 @@ struct Inner {
     sender: mpsc::Sender<TrackInfo>,
     lag_ms: Arc<AtomicU64>,
     ui_sched_id: Arc<AtomicU64>,
+    history: Arc<Mutex<Vec<TrackInfo>>>,
 }
 
+/// Follows a station on the LISTEN.moe gateway and sends each track to
+/// `sender` once it becomes audible, `lag_ms` after it started on air.
 #[derive(Debug)]
 pub struct Meta {
     inner: RefCell<Inner>,
@@ -49,16 +53,31 @@ impl Meta {
                 sender,
                 lag_ms,
                 ui_sched_id: Arc::new(AtomicU64::new(0)),
+                history: Arc::new(Mutex::new(Vec::new())),
             }),
         })
     }
 
+    /// Tracks seen by the gateway on the current station, oldest first. Includes
+    /// upcoming tracks that playback has not reached yet.
+    pub fn history(&self) -> Vec<TrackInfo> {
+        let inner = self.inner.borrow();
+        let history = match inner.history.lock() {
+            Ok(history) => history.clone(),
+            Err(_) => Vec::new(),
+        };
+        history
+    }
+
     pub fn set_station(&self, station: Station) {
         let mut inner = self.inner.borrow_mut();
         let was_running = matches!(inner.state, State::Running { .. });
         if was_running {
             Self::stop_inner(&mut inner);
         }
+        // The old worker may still push a track before it sees the stop, so
+        // leave it the old list instead of clearing the shared one.
+        inner.history = Arc::default();
         inner.station = station;
         if was_running {
             Self::start_inner(&mut inner);
@@ -96,19 +115,28 @@ impl Meta {
 
     fn start_inner(inner: &mut Inner) {
         match inner.state {
-            State::Running { .. } => return,
+            State::Running { .. } => {}
             State::Stopped => {
                 let (tx, rx) = mpsc::channel::<Control>();
                 let station = inner.station;
                 let sender = inner.sender.clone();
                 let lag_ms = inner.lag_ms.clone();
                 let ui_sched_id = inner.ui_sched_id.clone();
+                // Each worker gets its own list, for the same reason as in `set_station`.
+                let tracks = match inner.history.lock() {
+                    Ok(history) => history.clone(),
+                    Err(_) => Vec::new(),
+                };
+                let history = Arc::new(Mutex::new(tracks));
+                inner.history = history.clone();
 
                 inner.state = State::Running { tx: tx.clone() };
 
                 thread::spawn(move || {
-                    if let Err(err) = run_meta_loop(station, sender, rx, lag_ms, ui_sched_id) {
-                        eprintln!("Gateway error in metadata loop: {err}");
+                    if let Err(err) =
+                        run_meta_loop(station, sender, rx, lag_ms, ui_sched_id, history)
+                    {
+                        error!("Gateway error in metadata loop: {err}");
                     }
                 });
             }
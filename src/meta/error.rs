@@ -1,4 +1,4 @@
-/// Common error/result types for the metadata gateway loop.
+//! Common error/result types for the metadata gateway loop.
 
 pub type MetaError = Box<dyn std::error::Error + Send + Sync + 'static>;
 pub type MetaResult<T> = Result<T, MetaError>;
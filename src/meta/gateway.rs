@@ -1,3 +1,4 @@
+use log::{debug, info, warn};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::VecDeque;
@@ -5,18 +6,15 @@ use std::io::{Read, Write};
 use std::sync::mpsc;
 use std::sync::{
     atomic::{AtomicU64, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tungstenite::client::connect;
 use tungstenite::protocol::WebSocket;
 use tungstenite::stream::MaybeTlsStream;
 use tungstenite::Message;
 
-#[cfg(debug_assertions)]
-use crate::log::now_string;
-
 use super::controller::Control;
 use super::error::MetaResult;
 use super::schedule::{pick_track_for_playback, schedule_next_from_history, schedule_ui_switch};
@@ -36,10 +34,22 @@ struct GatewaySongPayload {
     song: Song,
     #[serde(rename = "startTime")]
     start_time: String,
+    #[serde(default)]
+    requester: Option<Requester>,
+    #[serde(rename = "lastPlayed", default)]
+    last_played: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Requester {
+    username: Option<String>,
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Song {
+    id: Option<u32>,
     title: Option<String>,
     #[serde(default)]
     artists: Vec<Artist>,
@@ -50,12 +60,15 @@ struct Song {
 
 #[derive(Debug, Deserialize)]
 struct Artist {
+    id: Option<u32>,
     name: Option<String>,
     image: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Album {
+    id: Option<u32>,
+    name: Option<String>,
     image: Option<String>,
 }
 
@@ -72,6 +85,7 @@ const OP_HELLO: u8 = 0;
 const OP_DISPATCH: u8 = 1;
 const OP_HEARTBEAT_ACK: u8 = 10;
 const EVENT_TRACK_UPDATE: &str = "TRACK_UPDATE";
+const HISTORY_LEN: usize = 32;
 
 /// Outer reconnect loop using blocking tungstenite.
 pub fn run_meta_loop(
@@ -80,6 +94,7 @@ pub fn run_meta_loop(
     rx: mpsc::Receiver<Control>,
     lag_ms: Arc<AtomicU64>,
     ui_sched_id: Arc<AtomicU64>,
+    shared_history: Arc<Mutex<Vec<TrackInfo>>>,
 ) -> MetaResult<()> {
     loop {
         if let Ok(Control::Stop) | Err(mpsc::TryRecvError::Disconnected) = rx.try_recv() {
@@ -91,6 +106,7 @@ pub fn run_meta_loop(
             &rx,
             lag_ms.clone(),
             ui_sched_id.clone(),
+            &shared_history,
         ) {
             Ok(()) => {
                 // Normal end (server closed the connection). Respect stop; otherwise retry.
@@ -101,7 +117,7 @@ pub fn run_meta_loop(
                 }
             }
             Err(err) => {
-                eprintln!("Gateway connection error: {err}, retrying in 5s…");
+                warn!("Gateway connection error: {err}, retrying in 5s…");
                 match rx.try_recv() {
                     Ok(Control::Stop) | Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
                     Err(mpsc::TryRecvError::Empty) => thread::sleep(Duration::from_secs(5)),
@@ -114,12 +130,15 @@ pub fn run_meta_loop(
 
 /// Single websocket session, with a simple heartbeat loop.
 /// Keeps history and does "snap-to-buffered-track" on Resume.
+/// The history is seeded from the gateway's `lastPlayed` list on the first update,
+/// so a reconnect while playback lags behind still resolves the right track.
 fn run_once(
     station: Station,
     sender: mpsc::Sender<TrackInfo>,
     rx: &mpsc::Receiver<Control>,
     lag_ms: Arc<AtomicU64>,
     ui_sched_id: Arc<AtomicU64>,
+    shared_history: &Mutex<Vec<TrackInfo>>,
 ) -> MetaResult<()> {
     if let Ok(Control::Stop) | Err(mpsc::TryRecvError::Disconnected) = rx.try_recv() {
         return Ok(());
@@ -128,8 +147,7 @@ fn run_once(
     let url = station.ws_url();
     let (mut ws, _response) = connect(url)?;
     set_maybe_tls_read_timeout(ws.get_mut(), Duration::from_millis(200))?;
-    #[cfg(debug_assertions)]
-    println!("[{}] Gateway connected to LISTEN.moe", now_string());
+    info!(station = station.name(); "Gateway connected to LISTEN.moe");
 
     // Read hello and get heartbeat interval (if any).
     let heartbeat_ms = read_hello_heartbeat(&mut ws)?;
@@ -145,7 +163,7 @@ fn run_once(
     let mut last_heartbeat_ack: Option<Instant> = heartbeat_dur.map(|_| Instant::now());
 
     let mut paused = false;
-    let mut history: VecDeque<TrackInfo> = VecDeque::with_capacity(32);
+    let mut history: VecDeque<TrackInfo> = VecDeque::with_capacity(HISTORY_LEN);
 
     loop {
         // Check for control messages first.
@@ -155,22 +173,19 @@ fn run_once(
                 break;
             }
             Ok(Control::Pause) => {
-                #[cfg(debug_assertions)]
-                println!("[{}] Pausing meta data", now_string());
+                debug!("Pausing meta data");
                 paused = true;
                 ui_sched_id.fetch_add(1, Ordering::Relaxed); // invalidate any pending scheduled sends
             }
             Ok(Control::Resume) => {
-                #[cfg(debug_assertions)]
-                println!("[{}] Resuming meta data", now_string());
+                debug!("Resuming meta data");
                 paused = false;
                 ui_sched_id.fetch_add(1, Ordering::Relaxed); // invalidate timers from before pause
 
                 // Snap UI to the track that matches buffered playback time.
                 let lag = lag_ms.load(Ordering::Relaxed);
-                #[cfg(debug_assertions)]
                 if let Some(t) = pick_track_for_playback(&history, lag) {
-                    println!("[{}] ui snap: {} - {}", now_string(), t.artist, t.title);
+                    debug!("UI snap: {} - {}", t.artist, t.title);
                 }
                 // Immediately snap UI to what playback should be on resume
                 if let Some(correct) = pick_track_for_playback(&history, lag) {
@@ -186,7 +201,7 @@ fn run_once(
         if let (Some(interval), Some(last)) = (heartbeat_dur, last_heartbeat.as_mut()) {
             if last.elapsed() >= interval {
                 if let Err(err) = ws.send(Message::Text(r#"{"op":9}"#.into())) {
-                    eprintln!("Gateway heartbeat send error: {err}");
+                    warn!("Gateway heartbeat send error: {err}");
                     break;
                 }
                 *last = Instant::now();
@@ -198,7 +213,7 @@ fn run_once(
             if let Some(ack) = last_heartbeat_ack.as_ref() {
                 let max_silence = Duration::from_millis(hb.saturating_mul(3));
                 if ack.elapsed() > max_silence {
-                    eprintln!(
+                    warn!(
                         "Gateway heartbeat ACK timeout (>{:?}); reconnecting…",
                         max_silence
                     );
@@ -209,7 +224,7 @@ fn run_once(
             // No heartbeat info from the server — fall back to a generic inactivity timeout.
             const MAX_INACTIVITY: Duration = Duration::from_secs(30);
             if last_any_msg.elapsed() > MAX_INACTIVITY {
-                eprintln!(
+                warn!(
                     "Gateway inactivity timeout (>{:?}); reconnecting…",
                     MAX_INACTIVITY
                 );
@@ -239,7 +254,7 @@ fn run_once(
         let env: GatewayEnvelope = match serde_json::from_str(&txt) {
             Ok(env) => env,
             Err(err) => {
-                eprintln!("Gateway JSON parse error: {err}");
+                warn!("Gateway JSON parse error: {err}");
                 continue;
             }
         };
@@ -249,35 +264,40 @@ fn run_once(
         match (env.op, env.t.as_deref()) {
             (OP_HEARTBEAT_ACK, _) => {
                 last_heartbeat_ack = Some(Instant::now());
-                #[cfg(debug_assertions)]
-                println!("[{}] Gateway heartbeat", now_string());
+                debug!("Gateway heartbeat");
             }
             (OP_DISPATCH, Some(EVENT_TRACK_UPDATE)) => {
-                if let Some(info) = parse_track_info(&env.d) {
-                    #[cfg(debug_assertions)]
-                    println!(
-                        "[{}] live track update: {} - {} (duration={})",
-                        now_string(),
-                        info.artist,
-                        info.title,
-                        info.duration_secs
+                if let Some((info, last_played)) = parse_track_update(&env.d) {
+                    debug!(
+                        artist = info.artist.as_str(),
+                        title = info.title.as_str(),
+                        duration = info.duration_secs;
+                        "Live track update"
                     );
-                    if history.len() == 32 {
+                    if history.is_empty() {
+                        debug!(
+                            "History seeded with {} lastPlayed tracks",
+                            last_played.len()
+                        );
+                        history.extend(last_played);
+                    }
+                    if history.len() == HISTORY_LEN {
                         history.pop_front();
                     }
                     history.push_back(info);
+                    if let Ok(mut shared) = shared_history.lock() {
+                        *shared = history.iter().cloned().collect();
+                    }
 
                     if !paused {
                         let lag = lag_ms.load(Ordering::Relaxed);
                         let my_id = ui_sched_id.fetch_add(1, Ordering::Relaxed) + 1;
-                        #[cfg(debug_assertions)]
-                        println!(
-                            "[{}] ui {} scheduled: {} - {} (lag_ms={})",
-                            now_string(),
-                            my_id,
+                        debug!(
+                            id = my_id,
+                            lag_ms = lag;
+                            "UI switch scheduled: {} - {}",
                             history.back().unwrap().artist,
-                            history.back().unwrap().title,
-                            lag
+                            history.back().unwrap().title
                         );
                         // Schedule the *new* track to appear when playback reaches it
                         schedule_ui_switch(
@@ -320,30 +340,59 @@ where
     }
 }
 
-/// Extract artist(s) + title from the gateway payload.
-fn parse_track_info(d: &Value) -> Option<TrackInfo> {
+/// Extract the current track, plus the `lastPlayed` tracks (oldest first) with start
+/// times inferred by walking back from the current start time.
+fn parse_track_update(d: &Value) -> Option<(TrackInfo, Vec<TrackInfo>)> {
     let payload: GatewaySongPayload = serde_json::from_value(d.clone()).ok()?;
+
+    let start_time_utc = parse_rfc3339_system_time(&payload.start_time)?;
+    let requester = payload
+        .requester
+        .and_then(|r| r.display_name.filter(|n| !n.is_empty()).or(r.username));
+    let current = track_from_song(payload.song, start_time_utc, requester);
+
+    let mut last_played = Vec::new();
+    let mut start = current.start_time_utc;
+    for song in payload.last_played.into_iter().take(HISTORY_LEN - 1) {
+        // Without a duration there is no way to know when earlier songs started.
+        let Some(prev_start) = song
+            .duration
+            .filter(|d| *d > 0)
+            .and_then(|d| start.checked_sub(Duration::from_secs(d as u64)))
+        else {
+            break;
+        };
+        start = prev_start;
+        last_played.push(track_from_song(song, start, None));
+    }
+    last_played.reverse();
+
+    Some((current, last_played))
+}
+
+/// Extract artist(s) + title from a song in the gateway payload.
+fn track_from_song(song: Song, start_time_utc: SystemTime, requester: Option<String>) -> TrackInfo {
     let Song {
+        id,
         title,
         artists,
         albums,
         duration,
-    } = payload.song;
+    } = song;
 
-    let start_time_utc = parse_rfc3339_system_time(&payload.start_time)?;
     let duration_secs = duration.unwrap_or(0);
 
     let title = title.unwrap_or_else(|| "unknown title".to_owned());
 
+    let names: Vec<String> = artists
+        .iter()
+        .filter_map(|a| a.name.as_deref())
+        .map(str::to_owned)
+        .collect();
     let artist = if artists.is_empty() {
         "Unknown artist".to_owned()
     } else {
-        artists
-            .iter()
-            .filter_map(|a| a.name.as_deref())
-            .map(str::to_owned)
-            .collect::<Vec<_>>()
-            .join(", ")
+        names.join(", ")
     };
 
     let album_cover = albums
@@ -356,14 +405,22 @@ fn parse_track_info(d: &Value) -> Option<TrackInfo> {
         .and_then(|a| a.image.as_deref())
         .map(|name| format!("{ARTIST_IMAGE_BASE}{name}"));
 
-    Some(TrackInfo {
+    let album = albums.first().and_then(|a| a.name.clone());
+
+    TrackInfo {
+        song_id: id,
+        artist_id: artists.first().and_then(|a| a.id),
+        album_id: albums.first().and_then(|a| a.id),
         artist,
+        artists: names,
         title,
+        album,
         album_cover,
         artist_image,
         start_time_utc,
         duration_secs,
-    })
+        requester,
+    }
 }
 
 fn set_maybe_tls_read_timeout(
@@ -1,3 +1,4 @@
+use log::debug;
 use std::collections::VecDeque;
 use std::sync::mpsc;
 use std::sync::{
@@ -80,14 +81,12 @@ pub fn schedule_next_from_history(
 
     let my_id = ui_sched_id.fetch_add(1, Ordering::Relaxed) + 1;
 
-    #[cfg(debug_assertions)]
-    println!(
-        "[{}] ui {} resched-next: {} - {} (lag_ms={})",
-        crate::log::now_string(),
-        my_id,
+    debug!(
+        id = my_id,
+        lag_ms = lag_ms;
+        "UI switch rescheduled: {} - {}",
         next.artist,
-        next.title,
-        lag_ms
+        next.title
     );
 
     schedule_ui_switch(sender, next, lag_ms, ui_sched_id, my_id);
@@ -1,15 +1,54 @@
-use std::time::SystemTime;
+use serde::{Serialize, Serializer};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const ALBUM_COVER_BASE: &str = "https://cdn.listen.moe/covers/";
 pub const ARTIST_IMAGE_BASE: &str = "https://cdn.listen.moe/artists/";
 
 /// Track info sent to the UI thread.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TrackInfo {
+    pub song_id: Option<u32>,
+    pub artist_id: Option<u32>,
+    pub album_id: Option<u32>,
+    /// All artists joined with ", ".
     pub artist: String,
+    pub artists: Vec<String>,
     pub title: String,
+    pub album: Option<String>,
     pub album_cover: Option<String>,
     pub artist_image: Option<String>,
+    #[serde(serialize_with = "unix_secs")]
     pub start_time_utc: SystemTime,
     pub duration_secs: u32,
+    /// Display name of the listener who requested this song, if any.
+    pub requester: Option<String>,
+}
+
+/// Unix time in seconds, which is easier to use from scripts than serde's default.
+fn unix_secs<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    serializer.serialize_u64(secs)
+}
+
+/// An empty track, for building one field by field.
+impl Default for TrackInfo {
+    fn default() -> Self {
+        Self {
+            song_id: None,
+            artist_id: None,
+            album_id: None,
+            artist: String::new(),
+            artists: Vec::new(),
+            title: String::new(),
+            album: None,
+            album_cover: None,
+            artist_image: None,
+            start_time_utc: UNIX_EPOCH,
+            duration_secs: 0,
+            requester: None,
+        }
+    }
 }
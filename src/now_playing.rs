@@ -0,0 +1,115 @@
+use dirs_next as dirs;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::atomic_file::write_atomic;
+use listenmoe::meta::TrackInfo;
+use listenmoe::APP_ID;
+
+const TEXT_FILE: &str = "now-playing.txt";
+const JSON_FILE: &str = "now-playing.json";
+/// Always PNG, whatever format the server sent, so the path never changes.
+const COVER_FILE: &str = "cover.png";
+
+/// `now-playing` in the user data dir.
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|base| base.join(APP_ID).join("now-playing"))
+}
+
+/// Fill in `{artist}`, `{title}`, `{album}`, `{requester}` and `{duration}`;
+/// a literal `\n` starts a new line.
+pub fn render(template: &str, info: &TrackInfo) -> String {
+    let duration = if info.duration_secs > 0 {
+        format!("{}:{:02}", info.duration_secs / 60, info.duration_secs % 60)
+    } else {
+        String::new()
+    };
+    template
+        .replace("\\n", "\n")
+        .replace("{artist}", &info.artist)
+        .replace("{title}", &info.title)
+        .replace("{album}", info.album.as_deref().unwrap_or_default())
+        .replace("{requester}", info.requester.as_deref().unwrap_or_default())
+        .replace("{duration}", &duration)
+}
+
+/// Write the text and JSON files for `info`.
+pub fn write_track(dir: &Path, template: &str, info: &TrackInfo) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomic(&dir.join(TEXT_FILE), render(template, info).as_bytes())?;
+    let json = serde_json::to_vec_pretty(info).map_err(io::Error::other)?;
+    write_atomic(&dir.join(JSON_FILE), &json)
+}
+
+/// Replace the cover, or remove it when the track has none.
+pub fn write_cover(dir: &Path, png: Option<&[u8]>) -> io::Result<()> {
+    match png {
+        Some(png) => {
+            fs::create_dir_all(dir)?;
+            write_atomic(&dir.join(COVER_FILE), png)
+        }
+        None => remove(&dir.join(COVER_FILE)),
+    }
+}
+
+/// Empty the files, so an overlay shows nothing while paused or stopped.
+pub fn clear(dir: &Path) -> io::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    write_atomic(&dir.join(TEXT_FILE), b"")?;
+    write_atomic(&dir.join(JSON_FILE), b"null")?;
+    remove(&dir.join(COVER_FILE))
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> TrackInfo {
+        TrackInfo {
+            artist: "LiSA".into(),
+            artists: vec!["LiSA".into()],
+            title: "紅蓮華".into(),
+            duration_secs: 245,
+            requester: Some("kitsune".into()),
+            ..TrackInfo::default()
+        }
+    }
+
+    #[test]
+    fn fills_in_every_field() {
+        let text = render("{artist} - {title} [{duration}] for {requester}", &track());
+        assert_eq!(text, "LiSA - 紅蓮華 [4:05] for kitsune");
+    }
+
+    #[test]
+    fn leaves_missing_fields_empty() {
+        let mut info = track();
+        info.duration_secs = 0;
+        info.requester = None;
+        assert_eq!(render("{album}|{duration}|{requester}", &info), "||");
+    }
+
+    #[test]
+    fn turns_escaped_newlines_into_lines() {
+        assert_eq!(render("{artist}\\n{title}", &track()), "LiSA\n紅蓮華");
+    }
+
+    #[test]
+    fn keeps_other_text() {
+        assert_eq!(
+            render("Now: {unknown} {artist}", &track()),
+            "Now: {unknown} LiSA"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use log::debug;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::AtomicU32;
+use std::sync::{mpsc, Arc};
+use std::time::SystemTime;
+
+use crate::listen::{Listen, StreamStatus};
+use crate::meta::{Meta, TrackInfo};
+use crate::station::Station;
+
+/// Where playback is, as one state for the UI and every integration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerState {
+    Stopped,
+    Connecting,
+    /// Connected; waiting for the first audio.
+    Buffering,
+    Playing,
+    Paused,
+    /// The connection dropped and the stream is being reopened.
+    Reconnecting,
+    /// The stream gave up, with the reason.
+    Error(String),
+}
+
+impl PlayerState {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            PlayerState::Stopped => "stopped",
+            PlayerState::Connecting => "connecting",
+            PlayerState::Buffering => "buffering",
+            PlayerState::Playing => "playing",
+            PlayerState::Paused => "paused",
+            PlayerState::Reconnecting => "reconnecting",
+            PlayerState::Error(_) => "error",
+        }
+    }
+
+    /// Whether playback was asked for, even if nothing is audible yet. Pausing
+    /// makes sense in these states, playing doesn't.
+    pub const fn is_active(&self) -> bool {
+        matches!(
+            self,
+            PlayerState::Connecting
+                | PlayerState::Buffering
+                | PlayerState::Playing
+                | PlayerState::Reconnecting
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerEvent {
+    State(PlayerState),
+    Station(Station),
+}
+
+type EventFn = Rc<dyn Fn(&PlayerEvent)>;
+
+/// The stream and metadata a [`Player`] drives, so its state handling can be
+/// tested without audio or network.
+trait Backend {
+    fn start(&self);
+    fn pause(&self);
+    fn stop(&self);
+    fn set_station(&self, station: Station);
+    fn station(&self) -> Station;
+    /// What the stream reported since the last call, oldest first.
+    fn poll_status(&self) -> Vec<StreamStatus>;
+}
+
+struct Radio {
+    listen: Rc<Listen>,
+    meta: Rc<Meta>,
+}
+
+impl Backend for Radio {
+    fn start(&self) {
+        self.meta.start();
+        self.listen.start();
+    }
+
+    fn pause(&self) {
+        self.meta.pause();
+        self.listen.pause();
+    }
+
+    fn stop(&self) {
+        self.meta.stop();
+        self.listen.stop();
+    }
+
+    fn set_station(&self, station: Station) {
+        self.listen.set_station(station);
+        self.meta.set_station(station);
+    }
+
+    fn station(&self) -> Station {
+        self.listen.get_station()
+    }
+
+    fn poll_status(&self) -> Vec<StreamStatus> {
+        let statuses = self.listen.poll_status();
+        // The stream stopped itself; the metadata follows
+        if statuses
+            .iter()
+            .any(|status| matches!(status, StreamStatus::Failed(_)))
+        {
+            self.meta.stop();
+        }
+        statuses
+    }
+}
+
+/// Plays a station and follows its metadata: owns [`Listen`] and [`Meta`] and
+/// keeps them in step, so callers only deal with one state.
+pub struct Player {
+    listen: Rc<Listen>,
+    meta: Rc<Meta>,
+    backend: Box<dyn Backend>,
+    state: RefCell<PlayerState>,
+    handlers: RefCell<Vec<EventFn>>,
+}
+
+impl Player {
+    /// Tracks are sent to `tracks` when they become audible.
+    pub fn new(station: Station, tracks: mpsc::Sender<TrackInfo>) -> Rc<Self> {
+        let listen = Listen::new(station);
+        let meta = Meta::new(station, tracks, listen.lag_ms());
+        let backend = Radio {
+            listen: listen.clone(),
+            meta: meta.clone(),
+        };
+        Self::with_backend(listen, meta, Box::new(backend))
+    }
+
+    fn with_backend(listen: Rc<Listen>, meta: Rc<Meta>, backend: Box<dyn Backend>) -> Rc<Self> {
+        Rc::new(Self {
+            listen,
+            meta,
+            backend,
+            state: RefCell::new(PlayerState::Stopped),
+            handlers: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// The spectrum bars as f32 bits, updated while playing.
+    pub fn spectrum_bars(&self) -> Arc<Vec<AtomicU32>> {
+        self.listen.spectrum_bars()
+    }
+
+    /// Latest mono samples as f32 bits, for drawing.
+    pub fn waveform(&self) -> Arc<Vec<AtomicU32>> {
+        self.listen.waveform()
+    }
+
+    /// Whether the stream computes the spectrum and waveform; turn it off while
+    /// nothing uses them.
+    pub fn set_analysis(&self, enabled: bool) {
+        self.listen.set_analysis(enabled);
+    }
+
+    /// Wall-clock time of the audio that is audible right now, frozen while paused.
+    pub fn playback_now(&self) -> SystemTime {
+        self.listen.playback_now()
+    }
+
+    /// Tracks seen on the current station, oldest first, including upcoming ones.
+    pub fn history(&self) -> Vec<TrackInfo> {
+        self.meta.history()
+    }
+
+    pub fn state(&self) -> PlayerState {
+        self.state.borrow().clone()
+    }
+
+    pub fn station(&self) -> Station {
+        self.backend.station()
+    }
+
+    /// Call `f` with every state and station change.
+    pub fn subscribe<F: Fn(&PlayerEvent) + 'static>(&self, f: F) {
+        self.handlers.borrow_mut().push(Rc::new(f));
+    }
+
+    pub fn play(&self) {
+        let state = self.state();
+        if state.is_active() {
+            return;
+        }
+        self.backend.start();
+        // A paused stream is still connected
+        self.set_state(if state == PlayerState::Paused {
+            PlayerState::Playing
+        } else {
+            PlayerState::Connecting
+        });
+    }
+
+    pub fn pause(&self) {
+        if !self.state().is_active() {
+            return;
+        }
+        self.backend.pause();
+        self.set_state(PlayerState::Paused);
+    }
+
+    pub fn stop(&self) {
+        self.backend.stop();
+        self.set_state(PlayerState::Stopped);
+    }
+
+    pub fn toggle(&self) {
+        if self.state().is_active() {
+            self.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    /// Switch to `station` and play it.
+    pub fn set_station(&self, station: Station) {
+        if station != self.station() {
+            self.backend.set_station(station);
+            self.emit(&PlayerEvent::Station(station));
+            // The new stream starts from scratch, even if the old one was paused
+            if self.state() == PlayerState::Paused {
+                self.set_state(PlayerState::Connecting);
+            }
+        }
+        self.play();
+    }
+
+    /// Pick up what the stream worker reported; call this regularly from the
+    /// thread that owns the player.
+    pub fn poll(&self) {
+        for status in self.backend.poll_status() {
+            let next = match status {
+                StreamStatus::Failed(err) => PlayerState::Error(err),
+                // Late reports from a stream that was paused or stopped since
+                _ if !self.state().is_active() => continue,
+                StreamStatus::Connecting => PlayerState::Connecting,
+                StreamStatus::Buffering => PlayerState::Buffering,
+                StreamStatus::Playing => PlayerState::Playing,
+                StreamStatus::Reconnecting => PlayerState::Reconnecting,
+            };
+            self.set_state(next);
+        }
+    }
+
+    fn set_state(&self, state: PlayerState) {
+        if *self.state.borrow() == state {
+            return;
+        }
+        debug!(state = state.name(); "Player state changed");
+        *self.state.borrow_mut() = state.clone();
+        self.emit(&PlayerEvent::State(state));
+    }
+
+    fn emit(&self, event: &PlayerEvent) {
+        // Handlers may call back into the player
+        let handlers: Vec<EventFn> = self.handlers.borrow().clone();
+        for handler in handlers {
+            handler(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Reports whatever the test queues, as the stream worker would.
+    struct FakeStream {
+        station: Cell<Station>,
+        statuses: Rc<RefCell<Vec<StreamStatus>>>,
+    }
+
+    impl Backend for FakeStream {
+        fn start(&self) {}
+        fn pause(&self) {}
+        fn stop(&self) {}
+
+        fn set_station(&self, station: Station) {
+            self.station.set(station);
+        }
+
+        fn station(&self) -> Station {
+            self.station.get()
+        }
+
+        fn poll_status(&self) -> Vec<StreamStatus> {
+            self.statuses.borrow_mut().drain(..).collect()
+        }
+    }
+
+    struct Fixture {
+        player: Rc<Player>,
+        statuses: Rc<RefCell<Vec<StreamStatus>>>,
+        events: Rc<RefCell<Vec<PlayerEvent>>>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let station = Station::Jpop;
+            let listen = Listen::new(station);
+            let meta = Meta::new(station, mpsc::channel().0, listen.lag_ms());
+            let statuses = Rc::new(RefCell::new(Vec::new()));
+            let backend = FakeStream {
+                station: Cell::new(station),
+                statuses: statuses.clone(),
+            };
+            let player = Player::with_backend(listen, meta, Box::new(backend));
+            let events = Rc::new(RefCell::new(Vec::new()));
+            {
+                let events = events.clone();
+                player.subscribe(move |event| events.borrow_mut().push(event.clone()));
+            }
+            Self {
+                player,
+                statuses,
+                events,
+            }
+        }
+
+        fn report(&self, statuses: &[StreamStatus]) {
+            self.statuses.borrow_mut().extend_from_slice(statuses);
+            self.player.poll();
+        }
+
+        fn playing() -> Self {
+            let fixture = Self::new();
+            fixture.player.play();
+            fixture.report(&[StreamStatus::Buffering, StreamStatus::Playing]);
+            assert_eq!(fixture.player.state(), PlayerState::Playing);
+            fixture.events.borrow_mut().clear();
+            fixture
+        }
+    }
+
+    #[test]
+    fn play_from_stopped_connects() {
+        let fixture = Fixture::new();
+        fixture.player.play();
+        assert_eq!(fixture.player.state(), PlayerState::Connecting);
+        fixture.report(&[StreamStatus::Buffering]);
+        assert_eq!(fixture.player.state(), PlayerState::Buffering);
+        fixture.report(&[StreamStatus::Playing]);
+        assert_eq!(fixture.player.state(), PlayerState::Playing);
+    }
+
+    #[test]
+    fn play_from_paused_resumes() {
+        let fixture = Fixture::playing();
+        fixture.player.pause();
+        assert_eq!(fixture.player.state(), PlayerState::Paused);
+        fixture.player.play();
+        assert_eq!(fixture.player.state(), PlayerState::Playing);
+        assert_eq!(
+            *fixture.events.borrow(),
+            [
+                PlayerEvent::State(PlayerState::Paused),
+                PlayerEvent::State(PlayerState::Playing),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_late_statuses_after_pause() {
+        let fixture = Fixture::playing();
+        fixture.player.pause();
+        fixture.report(&[StreamStatus::Reconnecting, StreamStatus::Playing]);
+        assert_eq!(fixture.player.state(), PlayerState::Paused);
+    }
+
+    #[test]
+    fn ignores_late_statuses_after_stop() {
+        let fixture = Fixture::playing();
+        fixture.player.stop();
+        fixture.report(&[StreamStatus::Buffering, StreamStatus::Playing]);
+        assert_eq!(fixture.player.state(), PlayerState::Stopped);
+    }
+
+    #[test]
+    fn failure_is_an_error_and_play_starts_over() {
+        let fixture = Fixture::playing();
+        fixture.report(&[StreamStatus::Failed("no route".into())]);
+        assert_eq!(
+            fixture.player.state(),
+            PlayerState::Error("no route".into())
+        );
+        assert!(!fixture.player.state().is_active());
+        fixture.player.play();
+        assert_eq!(fixture.player.state(), PlayerState::Connecting);
+    }
+
+    #[test]
+    fn pause_and_stop_twice_change_nothing() {
+        let fixture = Fixture::playing();
+        fixture.player.pause();
+        fixture.player.pause();
+        fixture.player.stop();
+        fixture.player.stop();
+        assert_eq!(
+            *fixture.events.borrow(),
+            [
+                PlayerEvent::State(PlayerState::Paused),
+                PlayerEvent::State(PlayerState::Stopped),
+            ]
+        );
+    }
+
+    #[test]
+    fn switching_station_while_paused_reconnects() {
+        let fixture = Fixture::playing();
+        fixture.player.pause();
+        fixture.events.borrow_mut().clear();
+        fixture.player.set_station(Station::Kpop);
+        assert_eq!(fixture.player.station(), Station::Kpop);
+        assert_eq!(fixture.player.state(), PlayerState::Connecting);
+        assert_eq!(
+            *fixture.events.borrow(),
+            [
+                PlayerEvent::Station(Station::Kpop),
+                PlayerEvent::State(PlayerState::Connecting),
+            ]
+        );
+    }
+
+    #[test]
+    fn selecting_the_paused_station_resumes() {
+        let fixture = Fixture::playing();
+        fixture.player.pause();
+        fixture.player.set_station(Station::Jpop);
+        assert_eq!(fixture.player.state(), PlayerState::Playing);
+    }
+}
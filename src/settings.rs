@@ -0,0 +1,258 @@
+use dirs_next as dirs;
+use log::{error, warn};
+use std::{
+    cell::{Cell, RefCell},
+    fs, io,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::atomic_file::write_atomic;
+use listenmoe::station::Station;
+use listenmoe::APP_ID;
+
+const FILE_NAME: &str = "settings.toml";
+
+pub const DEFAULT_STATION: &str = "default-station";
+pub const LAST_STATION: &str = "last-station";
+pub const RESTORE_STATION: &str = "restore-last-station";
+pub const AUTOPLAY: &str = "autoplay";
+pub const TINT_MODE: &str = "tint-mode";
+pub const VISUALIZER_STYLE: &str = "visualizer-style";
+pub const NOTIFICATIONS: &str = "notifications";
+pub const CLOSE_ACTION: &str = "close-action";
+pub const SHORTCUTS: &str = "shortcuts";
+pub const EXPORT_ENABLED: &str = "export-now-playing";
+pub const EXPORT_TEMPLATE: &str = "export-template";
+pub const EXPORT_DIR: &str = "export-dir";
+pub const EXPORT_CLEAR: &str = "export-clear-when-paused";
+pub const HTTP_SERVER: &str = "http-server";
+pub const HTTP_PORT: &str = "http-port";
+pub const LOG_FILE: &str = "log-file";
+
+#[derive(Debug, Clone, Copy)]
+enum DefaultValue {
+    Bool(bool),
+    Int(i32),
+    Str(&'static str),
+}
+
+/// Keys and defaults.
+const DEFAULTS: &[(&str, DefaultValue)] = &[
+    (DEFAULT_STATION, DefaultValue::Str("jpop")),
+    (LAST_STATION, DefaultValue::Str("jpop")),
+    (RESTORE_STATION, DefaultValue::Bool(false)),
+    (AUTOPLAY, DefaultValue::Bool(false)),
+    (TINT_MODE, DefaultValue::Str("cover")),
+    (VISUALIZER_STYLE, DefaultValue::Str("bars")),
+    (NOTIFICATIONS, DefaultValue::Str("unfocused")),
+    (CLOSE_ACTION, DefaultValue::Str("quit")),
+    (SHORTCUTS, DefaultValue::Str("")),
+    (EXPORT_ENABLED, DefaultValue::Bool(false)),
+    (EXPORT_TEMPLATE, DefaultValue::Str("{artist} - {title}")),
+    (EXPORT_DIR, DefaultValue::Str("")),
+    (EXPORT_CLEAR, DefaultValue::Bool(false)),
+    (HTTP_SERVER, DefaultValue::Bool(false)),
+    (HTTP_PORT, DefaultValue::Int(8780)),
+    (LOG_FILE, DefaultValue::Bool(false)),
+];
+
+/// What the close button does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseAction {
+    Quit,
+    /// Hide the window and keep playing.
+    Background,
+}
+
+impl CloseAction {
+    pub const ALL: [CloseAction; 2] = [CloseAction::Quit, CloseAction::Background];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            CloseAction::Quit => "quit",
+            CloseAction::Background => "background",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+/// How the window is tinted with the cover colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TintMode {
+    /// Tint, and switch to the light or dark style that suits the cover.
+    Cover,
+    /// Tint, but keep the system light or dark style.
+    System,
+    Off,
+}
+
+impl TintMode {
+    pub const ALL: [TintMode; 3] = [TintMode::Cover, TintMode::System, TintMode::Off];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            TintMode::Cover => "cover",
+            TintMode::System => "system",
+            TintMode::Off => "off",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+type ChangedFn = Rc<dyn Fn(&str)>;
+
+/// Returned by [`Settings::connect_changed`], to disconnect the handler again.
+pub struct HandlerId(u64);
+
+/// Persistent user preferences, in `settings.toml` under the XDG config dir.
+pub struct Settings {
+    values: RefCell<toml::Table>,
+    handlers: RefCell<Vec<(u64, ChangedFn)>>,
+    next_id: Cell<u64>,
+}
+
+impl Settings {
+    pub fn load() -> Rc<Self> {
+        Rc::new(Self {
+            values: RefCell::new(load_file()),
+            handlers: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        })
+    }
+
+    pub fn boolean(&self, key: &str) -> bool {
+        self.values
+            .borrow()
+            .get(key)
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(match default_value(key) {
+                Some(DefaultValue::Bool(b)) => b,
+                _ => false,
+            })
+    }
+
+    pub fn set_boolean(&self, key: &str, value: bool) {
+        self.set_value(key, toml::Value::Boolean(value));
+    }
+
+    pub fn int(&self, key: &str) -> i32 {
+        self.values
+            .borrow()
+            .get(key)
+            .and_then(toml::Value::as_integer)
+            .and_then(|i| i32::try_from(i).ok())
+            .unwrap_or(match default_value(key) {
+                Some(DefaultValue::Int(i)) => i,
+                _ => 0,
+            })
+    }
+
+    pub fn set_int(&self, key: &str, value: i32) {
+        self.set_value(key, toml::Value::Integer(value.into()));
+    }
+
+    pub fn string(&self, key: &str) -> String {
+        self.values
+            .borrow()
+            .get(key)
+            .and_then(toml::Value::as_str)
+            .map(str::to_owned)
+            .unwrap_or_else(|| match default_value(key) {
+                Some(DefaultValue::Str(s)) => s.to_owned(),
+                _ => String::new(),
+            })
+    }
+
+    pub fn set_string(&self, key: &str, value: &str) {
+        self.set_value(key, toml::Value::String(value.into()));
+    }
+
+    /// Call `f` with the key whenever a setting changes.
+    pub fn connect_changed<F: Fn(&str) + 'static>(&self, f: F) -> HandlerId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.handlers.borrow_mut().push((id, Rc::new(f)));
+        HandlerId(id)
+    }
+
+    /// Remove a handler added with [`Settings::connect_changed`].
+    pub fn disconnect(&self, id: HandlerId) {
+        self.handlers.borrow_mut().retain(|(n, _)| *n != id.0);
+    }
+
+    /// The station to start with: the last one played, or the default.
+    pub fn startup_station(&self) -> Station {
+        let key = if self.boolean(RESTORE_STATION) {
+            LAST_STATION
+        } else {
+            DEFAULT_STATION
+        };
+        Station::from_name(&self.string(key)).unwrap_or(Station::Jpop)
+    }
+
+    pub fn close_action(&self) -> CloseAction {
+        CloseAction::from_name(&self.string(CLOSE_ACTION)).unwrap_or(CloseAction::Quit)
+    }
+
+    pub fn tint_mode(&self) -> TintMode {
+        TintMode::from_name(&self.string(TINT_MODE)).unwrap_or(TintMode::Cover)
+    }
+
+    fn set_value(&self, key: &str, value: toml::Value) {
+        {
+            let mut values = self.values.borrow_mut();
+            if values.get(key) == Some(&value) {
+                return;
+            }
+            values.insert(key.to_owned(), value);
+            if let Err(err) = save_file(&values) {
+                error!("Failed to save settings: {err}");
+            }
+        }
+        // Handlers may read settings, so don't hold any borrow while calling them.
+        let handlers: Vec<ChangedFn> = self
+            .handlers
+            .borrow()
+            .iter()
+            .map(|(_, f)| f.clone())
+            .collect();
+        for handler in handlers {
+            handler(key);
+        }
+    }
+}
+
+fn default_value(key: &str) -> Option<DefaultValue> {
+    DEFAULTS.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|base| base.join(APP_ID).join(FILE_NAME))
+}
+
+fn load_file() -> toml::Table {
+    let Some(Ok(text)) = config_path().map(fs::read_to_string) else {
+        return toml::Table::new();
+    };
+    text.parse::<toml::Table>().unwrap_or_else(|err| {
+        warn!("Ignoring invalid settings file: {err}");
+        toml::Table::new()
+    })
+}
+
+fn save_file(values: &toml::Table) -> io::Result<()> {
+    let path = config_path().ok_or_else(|| io::Error::other("no config dir"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(values).map_err(io::Error::other)?;
+    // A crash mid-write must not leave the settings truncated
+    write_atomic(&path, text.as_bytes())
+}
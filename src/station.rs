@@ -1,10 +1,16 @@
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Station {
     Jpop,
     Kpop,
 }
 
 impl Station {
+    pub const ALL: [Station; 2] = [Station::Jpop, Station::Kpop];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
     pub fn stream_url(self) -> &'static str {
         match self {
             Station::Jpop => "https://listen.moe/stream",
@@ -2,29 +2,31 @@ use adw::glib;
 use adw::gtk::{
     self,
     gdk::Display,
-    gio::SimpleAction,
-    prelude::{ActionMapExt, GtkApplicationExt, GtkWindowExt, WidgetExt},
-    ApplicationWindow, Button,
+    gio::{Cancellable, FileCreateFlags, SimpleAction},
+    prelude::{ActionMapExt, GtkWindowExt, WidgetExt},
+    ApplicationWindow, Button, FileDialog,
 };
-use adw::{prelude::*, Application, WindowTitle};
+use adw::{prelude::*, WindowTitle};
 use gettextrs::gettext;
+use log::{error, warn};
 #[cfg(target_os = "linux")]
 use mpris_server::PlaybackStatus;
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
 #[cfg(target_os = "linux")]
 use std::sync::mpsc;
 
 #[cfg(target_os = "linux")]
 use super::controls::{build_controls, MediaControlEvent, MediaControls};
-use crate::listen::Listen;
-use crate::meta::Meta;
-use crate::station::Station;
+use super::notify::mode_menu;
+use super::request::present_request_dialog;
+use crate::cover_cache;
+use listenmoe::logging;
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::{Player, PlayerEvent, PlayerState};
+use listenmoe::station::Station;
+use listenmoe::APP_ID;
 
 const APP_NAME: &str = "Listen Moe";
-#[cfg(debug_assertions)]
-const APP_ID: &str = "io.github.noobping.listenmoe_beta";
-#[cfg(not(debug_assertions))]
-const APP_ID: &str = "io.github.noobping.listenmoe";
 
 fn make_action<F>(name: &str, f: F) -> SimpleAction
 where
@@ -38,86 +40,38 @@ where
 #[cfg(target_os = "linux")]
 pub fn build_actions(
     window: &ApplicationWindow,
-    app: &Application,
     win_title: &WindowTitle,
     play_button: &Button,
     pause_button: &Button,
-    radio: &Rc<Listen>,
-    meta: &Rc<Meta>,
+    player: &Rc<Player>,
+    current_track: &Rc<RefCell<Option<TrackInfo>>>,
 ) -> (
     Option<Rc<MediaControls>>,
     Option<mpsc::Receiver<MediaControlEvent>>,
 ) {
     let (controls, ctrl_rx) = {
-        match build_controls(APP_ID, APP_NAME, APP_ID) {
+        match build_controls(APP_ID, APP_NAME, APP_ID, player.station()) {
             Ok((controls, ctrl_rx)) => (Some(controls), Some(ctrl_rx)),
             Err(e) => {
-                eprintln!("Media control unavailable: {e}");
+                warn!("Media control unavailable: {e}");
                 (None, None)
             }
         }
     };
-    let set_playback = {
-        let controls = controls.clone();
-        move |status| {
-            if let Some(c) = controls.as_ref() {
-                c.set_playback(status);
-            }
-        }
-    };
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        let set_playback = set_playback.clone();
-        make_action("play", move || {
-            win.set_title(APP_NAME);
-            win.set_subtitle("Connecting...");
-            meta.start();
-            radio.start();
-            play.set_visible(false);
-            pause.set_visible(true);
-            set_playback(PlaybackStatus::Playing);
-        })
-    });
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        let set_playback = set_playback.clone();
-        make_action("pause", move || {
-            meta.pause();
-            radio.pause();
-            pause.set_visible(false);
-            play.set_visible(true);
-            win.set_title(APP_NAME);
-            win.set_subtitle(&gettext("J-POP and K-POP radio"));
-            set_playback(PlaybackStatus::Paused);
-        })
-    });
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        let set_playback = set_playback.clone();
-        make_action("stop", move || {
-            meta.stop();
-            radio.stop();
-            pause.set_visible(false);
-            play.set_visible(true);
-            win.set_title(APP_NAME);
-            win.set_subtitle(&gettext("J-POP and K-POP radio"));
-            set_playback(PlaybackStatus::Stopped);
-        })
-    });
-    add_actions(window, win_title, play_button, pause_button, radio, meta);
-    add_accels(app);
+    if let Some(c) = controls.clone() {
+        player.subscribe(move |event| match event {
+            PlayerEvent::State(state) => c.set_playback(playback_status(state)),
+            PlayerEvent::Station(station) => c.set_station(*station),
+        });
+    }
+    add_actions(
+        window,
+        win_title,
+        play_button,
+        pause_button,
+        player,
+        current_track,
+    );
 
     (controls, ctrl_rx)
 }
@@ -125,60 +79,29 @@ pub fn build_actions(
 #[cfg(not(target_os = "linux"))]
 pub fn build_actions(
     window: &ApplicationWindow,
-    app: &Application,
     win_title: &WindowTitle,
     play_button: &Button,
     pause_button: &Button,
-    radio: &Rc<Listen>,
-    meta: &Rc<Meta>,
+    player: &Rc<Player>,
+    current_track: &Rc<RefCell<Option<TrackInfo>>>,
 ) {
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        make_action("play", move || {
-            win.set_title("Listen Moe");
-            win.set_subtitle("Connecting...");
-            meta.start();
-            radio.start();
-            play.set_visible(false);
-            pause.set_visible(true);
-        })
-    });
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        make_action("pause", move || {
-            meta.pause();
-            radio.pause();
-            pause.set_visible(false);
-            play.set_visible(true);
-            win.set_title("Listen Moe");
-            win.set_subtitle(&gettext("J-POP and K-POP radio"));
-        })
-    });
-    window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win = win_title.clone();
-        let play = play_button.clone();
-        let stop = pause_button.clone();
-        make_action("stop", move || {
-            meta.stop();
-            radio.stop();
-            stop.set_visible(false);
-            play.set_visible(true);
-            win.set_title("Listen Moe");
-            win.set_subtitle(&gettext("J-POP and K-POP radio"));
-        })
-    });
-    add_actions(window, win_title, play_button, pause_button, radio, meta);
-    add_accels(app);
+    add_actions(
+        window,
+        win_title,
+        play_button,
+        pause_button,
+        player,
+        current_track,
+    );
+}
+
+#[cfg(target_os = "linux")]
+fn playback_status(state: &PlayerState) -> PlaybackStatus {
+    match state {
+        PlayerState::Paused => PlaybackStatus::Paused,
+        PlayerState::Stopped | PlayerState::Error(_) => PlaybackStatus::Stopped,
+        _ => PlaybackStatus::Playing,
+    }
 }
 
 fn add_actions(
@@ -186,12 +109,59 @@ fn add_actions(
     win_title: &WindowTitle,
     play_button: &Button,
     pause_button: &Button,
-    radio: &Rc<Listen>,
-    meta: &Rc<Meta>,
+    player: &Rc<Player>,
+    current_track: &Rc<RefCell<Option<TrackInfo>>>,
 ) {
+    // The buttons and the title follow the player
+    {
+        let win = win_title.clone();
+        let play = play_button.clone();
+        let pause = pause_button.clone();
+        player.subscribe(move |event| {
+            let PlayerEvent::State(state) = event else {
+                return;
+            };
+            play.set_visible(!state.is_active());
+            pause.set_visible(state.is_active());
+            match state {
+                PlayerState::Connecting => {
+                    win.set_title(APP_NAME);
+                    win.set_subtitle(&gettext("Connecting…"));
+                    win.set_tooltip_text(None);
+                }
+                PlayerState::Paused | PlayerState::Stopped => {
+                    win.set_title(APP_NAME);
+                    win.set_subtitle(&gettext("J-POP and K-POP radio"));
+                }
+                PlayerState::Error(err) => {
+                    win.set_title(APP_NAME);
+                    win.set_subtitle(&gettext("Playback failed"));
+                    win.set_tooltip_text(Some(err));
+                }
+                _ => {}
+            }
+        });
+    }
+    window.add_action(&{
+        let player = player.clone();
+        make_action("play", move || player.play())
+    });
+    window.add_action(&{
+        let player = player.clone();
+        make_action("pause", move || player.pause())
+    });
+    window.add_action(&{
+        let player = player.clone();
+        make_action("stop", move || player.stop())
+    });
     window.add_action(&{
         let win = window.clone();
-        make_action("quit", move || win.close())
+        // Closing the window may only hide it, so quit the application directly
+        make_action("quit", move || {
+            if let Some(app) = win.application() {
+                app.quit();
+            }
+        })
     });
     window.add_action(&{
         let win_clone = window.clone();
@@ -221,37 +191,23 @@ fn add_actions(
         })
     });
     window.add_action(&{
-        let play = play_button.clone();
-        let pause = pause_button.clone();
-        let win_clone = window.clone();
-        make_action("toggle", move || {
-            if play.is_visible() {
-                let _ = adw::prelude::WidgetExt::activate_action(
-                    &win_clone,
-                    "win.play",
-                    None::<&glib::Variant>,
-                );
-            } else if pause.is_visible() {
-                let _ = adw::prelude::WidgetExt::activate_action(
-                    &win_clone,
-                    "win.pause",
-                    None::<&glib::Variant>,
-                );
-            }
-        })
+        let player = player.clone();
+        make_action("toggle", move || player.toggle())
     });
     window.add_action(&{
-        let win = win_title.clone();
+        let current = current_track.clone();
         make_action("copy", move || {
-            let artist = win.title();
-            let title = win.subtitle();
+            let Some(info) = current.borrow().clone() else {
+                return;
+            };
+            let (artist, title) = (info.artist, info.title);
             if artist.is_empty() && title.is_empty() {
                 return;
             }
             let text = if artist.is_empty() {
-                title.to_string()
+                title
             } else if title.is_empty() {
-                artist.to_string()
+                artist
             } else {
                 format!("{artist}, {title}")
             };
@@ -262,71 +218,74 @@ fn add_actions(
         })
     });
     window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let win_clone = window.clone();
-        let play = play_button.clone();
+        let action = SimpleAction::new("copy_text", Some(glib::VariantTy::STRING));
+        action.connect_activate(|_, param| {
+            let Some(text) = param.and_then(|p| p.str()) else {
+                return;
+            };
+            if let Some(display) = Display::default() {
+                display.clipboard().set_text(text);
+            }
+        });
+        action
+    });
+    window.add_action(&{
+        let win = window.clone();
+        let player = player.clone();
+        let session = Rc::new(RefCell::new(None));
+        make_action("request", move || {
+            present_request_dialog(&win, &session, player.station())
+        })
+    });
+    window.add_action(&make_action("clear_cache", || {
+        if let Err(err) = cover_cache::clear() {
+            error!("Failed to clear the cover cache: {err}");
+        }
+    }));
+    window.add_action(&{
+        let win = window.clone();
+        make_action("export_log", move || export_log(&win))
+    });
+    window.add_action(&{
+        let player = player.clone();
         make_action("next_station", move || {
-            if play.is_visible() {
-                let _ = adw::prelude::WidgetExt::activate_action(
-                    &win_clone,
-                    "win.play",
-                    None::<&glib::Variant>,
-                );
+            if !player.state().is_active() {
+                player.play();
                 return;
             }
-            let current = radio.get_station();
-            let next = other_station(current);
-            radio.set_station(next);
-            meta.set_station(next);
+            player.set_station(other_station(player.station()));
         })
     });
     window.add_action(&{
-        let radio = radio.clone();
-        let meta = meta.clone();
-        let play = play_button.clone();
+        let player = player.clone();
         make_action("prev_station", move || {
-            if play.is_visible() {
+            if !player.state().is_active() {
                 return; // paused -> do nothing
             }
-            let current = radio.get_station();
-            let prev = other_station(current);
-            radio.set_station(prev);
-            meta.set_station(prev);
+            player.set_station(other_station(player.station()));
         })
     });
 }
 
-fn add_accels(app: &Application) {
-    app.set_accels_for_action("win.about", &["F1"]);
-    app.set_accels_for_action("win.copy", &["<primary>c"]);
-    app.set_accels_for_action("win.jpop", &["<primary>j"]);
-    app.set_accels_for_action("win.kpop", &["<primary>k"]);
-    app.set_accels_for_action("win.quit", &["<primary>q", "Escape"]);
-    app.set_accels_for_action("win.prev_station", &["<primary>z", "XF86AudioPrev"]);
-    app.set_accels_for_action(
-        "win.next_station",
-        &["<primary>y", "<primary><shift>z", "XF86AudioNext"],
-    );
-    app.set_accels_for_action(
-        "win.toggle",
-        &["<primary>p", "space", "Return", "<primary>s"],
-    );
-    app.set_accels_for_action("win.play", &["XF86AudioPlay"]);
-    app.set_accels_for_action("win.stop", &["XF86AudioStop"]);
-    app.set_accels_for_action("win.pause", &["XF86AudioPause"]);
-}
-
 pub fn populate_menu(
     window: &ApplicationWindow,
-    play_button: &Button,
     menu: &gtk::gio::Menu,
-    radio: &Rc<Listen>,
-    meta: &Rc<Meta>,
+    recent_menu: &gtk::gio::Menu,
+    lookup_menu: &gtk::gio::Menu,
+    player: &Rc<Player>,
 ) {
     menu.append(Some(&gettext("Copy title & artist")), Some("win.copy"));
+    menu.append_submenu(Some(&gettext("Open in…")), lookup_menu);
+    menu.append_submenu(Some(&gettext("Recently played")), recent_menu);
+    menu.append(Some(&gettext("Request a song…")), Some("win.request"));
+    menu.append(Some(&gettext("Show lyrics")), Some("win.lyrics"));
+    menu.append(Some(&gettext("Expanded view")), Some("win.expand"));
+    menu.append(
+        Some(&gettext("Next visualizer style")),
+        Some("win.cycle_visualizer"),
+    );
     for station in [Station::Jpop, Station::Kpop] {
-        let action = create_station_action(station, &play_button, &window, &radio, &meta);
+        let action = create_station_action(station, player);
         window.add_action(&action);
         menu.append(
             Some(
@@ -337,33 +296,67 @@ pub fn populate_menu(
             Some(&format!("win.{}", station.name())),
         );
     }
+    menu.append_submenu(Some(&gettext("Notifications")), &mode_menu());
+    menu.append(Some(&gettext("Preferences")), Some("win.preferences"));
+    menu.append(Some(&gettext("Keyboard Shortcuts")), Some("win.shortcuts"));
     menu.append(Some(&gettext("About")), Some("win.about"));
     menu.append(Some(&gettext("Quit")), Some("win.quit"));
 }
 
-fn create_station_action(
-    station: Station,
-    play_button: &Button,
-    window: &ApplicationWindow,
-    radio: &Rc<Listen>,
-    meta: &Rc<Meta>,
-) -> SimpleAction {
-    let radio = radio.clone();
-    let meta = meta.clone();
-    let win_clone = window.clone();
-    let play = play_button.clone();
+/// Rebuild the "Recently played" submenu from the gateway history, newest first.
+/// Tracks that start after `current` have not been heard yet and are left out.
+pub fn fill_recent_menu(menu: &gtk::gio::Menu, history: &[TrackInfo], current: &TrackInfo) {
+    const MAX_ITEMS: usize = 10;
 
-    make_action(station.name(), move || {
-        radio.set_station(station);
-        meta.set_station(station);
-        if play.is_visible() {
-            let _ = adw::prelude::WidgetExt::activate_action(
-                &win_clone,
-                "win.play",
-                None::<&glib::Variant>,
-            );
+    menu.remove_all();
+    for track in history
+        .iter()
+        .rev()
+        .filter(|t| t.start_time_utc < current.start_time_utc)
+        .take(MAX_ITEMS)
+    {
+        let label = format!("{} — {}", track.artist, track.title);
+        let item = gtk::gio::MenuItem::new(Some(&label), None);
+        item.set_action_and_target_value(
+            Some("win.copy_text"),
+            Some(&format!("{}, {}", track.artist, track.title).to_variant()),
+        );
+        menu.append_item(&item);
+    }
+}
+
+/// Save the log of this session, to attach to a bug report.
+fn export_log(window: &ApplicationWindow) {
+    let dialog = FileDialog::builder()
+        .title(gettext("Export debug log"))
+        .initial_name("listenmoe-debug.log")
+        .modal(true)
+        .build();
+    dialog.save(Some(window), None::<&Cancellable>, |res| {
+        let file = match res {
+            Ok(file) => file,
+            Err(_) => return, // dismissed
+        };
+        let text = format!(
+            "{APP_NAME} {}\n{}",
+            env!("CARGO_PKG_VERSION"),
+            logging::recent()
+        );
+        if let Err(err) = file.replace_contents(
+            text.as_bytes(),
+            None,
+            false,
+            FileCreateFlags::REPLACE_DESTINATION,
+            None::<&Cancellable>,
+        ) {
+            error!("Failed to export the debug log: {err}");
         }
-    })
+    });
+}
+
+fn create_station_action(station: Station, player: &Rc<Player>) -> SimpleAction {
+    let player = player.clone();
+    make_action(station.name(), move || player.set_station(station))
 }
 
 fn other_station(s: Station) -> Station {
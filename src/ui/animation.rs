@@ -0,0 +1,209 @@
+use adw::{
+    glib,
+    gtk::{self, gio::PowerProfileMonitor, ApplicationWindow},
+    prelude::*,
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::expanded::ExpandedView;
+use super::viz::{self, VizHandle, VizStyle};
+use listenmoe::player::{Player, PlayerEvent};
+
+const N_BARS: usize = 48;
+
+/// Runs the music animation, only while playing in a visible, focused window.
+pub struct Animation {
+    window: ApplicationWindow,
+    /// Weak, as the player keeps our subscription
+    player: Weak<Player>,
+    viz: gtk::DrawingArea,
+    handle: VizHandle,
+    expanded: Rc<ExpandedView>,
+    spectrum_bits: Arc<Vec<AtomicU32>>,
+    waveform_bits: Arc<Vec<AtomicU32>>,
+    /// UI-side smoothing of the bars
+    smooth: RefCell<Vec<f32>>,
+    /// Whether overlays read the spectrum from the local server.
+    serving: Cell<bool>,
+    gtk_settings: Option<gtk::Settings>,
+    power: PowerProfileMonitor,
+    /// The running timer and its interval in ms
+    timer: RefCell<Option<(glib::SourceId, u64)>>,
+}
+
+impl Animation {
+    pub fn new(
+        window: &ApplicationWindow,
+        player: &Rc<Player>,
+        expanded: &Rc<ExpandedView>,
+        height: i32,
+    ) -> Rc<Self> {
+        let (viz, handle) = viz::make_bars_visualizer(N_BARS, height);
+        let spectrum_bits = player.spectrum_bars();
+        let animation = Rc::new(Self {
+            window: window.clone(),
+            player: Rc::downgrade(player),
+            viz,
+            handle,
+            expanded: expanded.clone(),
+            smooth: RefCell::new(vec![0.0; spectrum_bits.len()]),
+            spectrum_bits,
+            waveform_bits: player.waveform(),
+            serving: Cell::new(false),
+            gtk_settings: gtk::Settings::default(),
+            power: PowerProfileMonitor::get_default(),
+            timer: RefCell::new(None),
+        });
+
+        let update = {
+            let animation = Rc::downgrade(&animation);
+            move || {
+                if let Some(animation) = animation.upgrade() {
+                    animation.update();
+                }
+            }
+        };
+        {
+            let update = update.clone();
+            window.connect_is_active_notify(move |_| update());
+        }
+        {
+            let update = update.clone();
+            window.connect_visible_notify(move |_| update());
+        }
+        {
+            let update = update.clone();
+            window.connect_suspended_notify(move |_| update());
+        }
+        {
+            let update = update.clone();
+            player.subscribe(move |event| {
+                if let PlayerEvent::State(_) = event {
+                    update();
+                }
+            });
+        }
+        {
+            let update = update.clone();
+            animation
+                .power
+                .connect_power_saver_enabled_notify(move |_| update());
+        }
+        if let Some(gtk_settings) = &animation.gtk_settings {
+            gtk_settings.connect_gtk_enable_animations_notify(move |_| update());
+        }
+        animation
+    }
+
+    /// The bars, to put behind the headerbar.
+    pub fn widget(&self) -> &gtk::DrawingArea {
+        &self.viz
+    }
+
+    pub fn style(&self) -> VizStyle {
+        self.handle.style()
+    }
+
+    pub fn set_style(self: &Rc<Self>, style: VizStyle) {
+        self.handle.set_style(style);
+        self.viz.set_visible(style != VizStyle::Off);
+        self.viz.queue_draw();
+        self.expanded.set_viz_style(style);
+        self.update();
+    }
+
+    pub fn set_serving(self: &Rc<Self>, serving: bool) {
+        self.serving.set(serving);
+        self.update();
+    }
+
+    /// Start, retime or stop the timer and the analysis to match the window and the player.
+    fn update(self: &Rc<Self>) {
+        let Some(player) = self.player.upgrade() else {
+            return;
+        };
+        let animations = self
+            .gtk_settings
+            .as_ref()
+            .is_none_or(|s| s.is_gtk_enable_animations());
+        let animate = animations
+            && self.style() != VizStyle::Off
+            && self.window.is_visible()
+            && self.window.is_active()
+            && !self.window.is_suspended()
+            && player.state().is_active();
+        // Overlays read the spectrum from the local server, whatever the window does
+        let serving = self.serving.get() && player.state().is_active();
+        player.set_analysis(animate || serving);
+
+        // Power saver: a lower frame rate is plenty for the background bars
+        let interval = if self.power.is_power_saver_enabled() {
+            100
+        } else {
+            33
+        };
+        let wanted = animate.then_some(interval);
+        let mut timer = self.timer.borrow_mut();
+        if timer.as_ref().map(|(_, ms)| *ms) == wanted {
+            return;
+        }
+        if let Some((source, _)) = timer.take() {
+            source.remove();
+        }
+        match wanted {
+            Some(ms) => {
+                let animation = Rc::downgrade(self);
+                let source = glib::timeout_add_local(Duration::from_millis(ms), move || {
+                    let Some(animation) = animation.upgrade() else {
+                        return glib::ControlFlow::Break;
+                    };
+                    animation.tick();
+                    glib::ControlFlow::Continue
+                });
+                *timer = Some((source, ms));
+            }
+            None => self.flatten(),
+        }
+    }
+
+    fn tick(&self) {
+        if !self.viz.is_visible() {
+            return;
+        }
+        let mut smooth = self.smooth.borrow_mut();
+        for (value, bits) in smooth.iter_mut().zip(self.spectrum_bits.iter()) {
+            let bar = f32::from_bits(bits.load(Ordering::Relaxed)).clamp(0.0, 1.0);
+            *value = *value * 0.70 + bar * 0.30;
+        }
+        let wave: Vec<f32> = self
+            .waveform_bits
+            .iter()
+            .map(|w| f32::from_bits(w.load(Ordering::Relaxed)))
+            .collect();
+
+        self.handle.set_values(&smooth);
+        self.handle.set_waveform(&wave);
+        self.viz.queue_draw();
+        self.expanded.set_viz_values(&smooth, &wave);
+    }
+
+    /// Leave the bars flat while the animation is stopped.
+    fn flatten(&self) {
+        let mut smooth = self.smooth.borrow_mut();
+        if smooth.iter().any(|v| *v != 0.0) {
+            smooth.fill(0.0);
+            self.handle.set_values(&smooth);
+            self.handle.set_waveform(&[]);
+            self.viz.queue_draw();
+            self.expanded.set_viz_values(&smooth, &[]);
+        }
+    }
+}
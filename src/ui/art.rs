@@ -0,0 +1,302 @@
+use adw::glib;
+use adw::gtk::{
+    self,
+    gdk::{Display, Texture},
+    gio::{self, Cancellable, FileCreateFlags, SimpleAction},
+    ApplicationWindow, Button, FileDialog, FileLauncher, GestureClick, Orientation, Picture,
+    Popover,
+};
+use adw::prelude::*;
+use gettextrs::gettext;
+use log::error;
+use std::{cell::RefCell, fs, rc::Rc, sync::mpsc, thread};
+
+use crate::cover_cache;
+use listenmoe::meta::TrackInfo;
+use listenmoe::APP_ID;
+
+/// A downloaded cover with the URL it came from, or the error.
+pub type CoverResult = Result<(String, Vec<u8>), String>;
+
+/// The cover popover under the title, with actions for the original image.
+pub struct CoverPopover {
+    popover: Popover,
+    picture: Picture,
+    switch_button: Button,
+    /// Album cover and artist image, whichever the track has.
+    sources: RefCell<Vec<String>>,
+    current: RefCell<Option<String>>,
+    bytes: RefCell<Option<glib::Bytes>>,
+    texture: RefCell<Option<Texture>>,
+    file_stem: RefCell<String>,
+    tx: mpsc::Sender<CoverResult>,
+}
+
+impl CoverPopover {
+    pub fn new(parent: &impl IsA<gtk::Widget>, tx: mpsc::Sender<CoverResult>) -> Rc<Self> {
+        let picture = Picture::builder()
+            .can_shrink(true)
+            .focusable(false)
+            .alternative_text(gettext("Cover art"))
+            .build();
+
+        let icon_button = |icon: &str, action: &str, tooltip: &str| {
+            Button::builder()
+                .icon_name(icon)
+                .action_name(action)
+                .tooltip_text(tooltip)
+                .build()
+        };
+        let switch_button = icon_button(
+            "media-playlist-repeat-symbolic",
+            "win.switch_cover",
+            &gettext("Show artist image"),
+        );
+        let buttons = gtk::Box::new(Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::Center);
+        buttons.append(&icon_button(
+            "document-save-symbolic",
+            "win.save_cover",
+            &gettext("Save cover…"),
+        ));
+        buttons.append(&icon_button(
+            "edit-copy-symbolic",
+            "win.copy_cover",
+            &gettext("Copy image"),
+        ));
+        buttons.append(&icon_button(
+            "image-x-generic-symbolic",
+            "win.open_cover",
+            &gettext("Open in image viewer"),
+        ));
+        buttons.append(&switch_button);
+
+        let content = gtk::Box::new(Orientation::Vertical, 6);
+        content.append(&picture);
+        content.append(&buttons);
+
+        let popover = Popover::builder()
+            .has_arrow(true)
+            .position(gtk::PositionType::Bottom)
+            .autohide(true)
+            .child(&content)
+            .build();
+        popover.set_parent(parent);
+        popover.add_css_class("cover-tint");
+
+        // Clicking the image itself still closes the popover.
+        let close_click = GestureClick::new();
+        {
+            let popover = popover.clone();
+            close_click.connect_released(move |_, _, _, _| popover.popdown());
+        }
+        picture.add_controller(close_click);
+
+        Rc::new(Self {
+            popover,
+            picture,
+            switch_button,
+            sources: RefCell::new(Vec::new()),
+            current: RefCell::new(None),
+            bytes: RefCell::new(None),
+            texture: RefCell::new(None),
+            file_stem: RefCell::new(String::new()),
+            tx,
+        })
+    }
+
+    pub fn toggle(&self) {
+        if self.popover.is_visible() {
+            self.popover.popdown();
+        } else if self.picture.paintable().is_some() {
+            self.popover.popup();
+        }
+    }
+
+    /// Start loading the new track's artwork. Returns false when it has none.
+    pub fn track_changed(&self, info: &TrackInfo) -> bool {
+        let sources: Vec<String> = [&info.album_cover, &info.artist_image]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        *self.file_stem.borrow_mut() = match (info.artist.is_empty(), info.title.is_empty()) {
+            (false, false) => format!("{} - {}", info.artist, info.title),
+            (true, false) => info.title.clone(),
+            (false, true) => info.artist.clone(),
+            (true, true) => "cover".to_owned(),
+        };
+        self.switch_button.set_visible(sources.len() > 1);
+        self.switch_button
+            .set_tooltip_text(Some(&gettext("Show artist image")));
+        let first = sources.first().cloned();
+        *self.sources.borrow_mut() = sources;
+        match first {
+            Some(url) => {
+                self.load(url);
+                true
+            }
+            None => {
+                *self.current.borrow_mut() = None;
+                false
+            }
+        }
+    }
+
+    /// Whether `url` is the artwork that should be shown, so late downloads
+    /// for a previous track can be dropped.
+    pub fn is_current(&self, url: &str) -> bool {
+        self.current.borrow().as_deref() == Some(url)
+    }
+
+    /// Show the scaled `preview`, and keep the original bytes and `full` texture for the actions.
+    pub fn set_cover(&self, bytes: &glib::Bytes, preview: &Texture, full: &Texture) {
+        self.picture.set_paintable(Some(preview));
+        *self.bytes.borrow_mut() = Some(bytes.clone());
+        *self.texture.borrow_mut() = Some(full.clone());
+    }
+
+    pub fn clear(&self) {
+        self.picture.set_paintable(None::<&gtk::gdk::Paintable>);
+        self.bytes.borrow_mut().take();
+        self.texture.borrow_mut().take();
+        self.popover.popdown();
+    }
+
+    fn load(&self, url: String) {
+        *self.current.borrow_mut() = Some(url.clone());
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let result = cover_cache::fetch(&url)
+                .map(|bytes| (url, bytes))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+
+    /// Show the other source when the track has both an album cover and an artist image.
+    fn switch_source(&self) {
+        let next = {
+            let sources = self.sources.borrow();
+            let current = self.current.borrow();
+            let i = sources
+                .iter()
+                .position(|s| Some(s) == current.as_ref())
+                .unwrap_or(0);
+            if sources.len() < 2 {
+                return;
+            }
+            sources[(i + 1) % sources.len()].clone()
+        };
+        let is_album = self.sources.borrow().first() == Some(&next);
+        self.switch_button.set_tooltip_text(Some(&if is_album {
+            gettext("Show artist image")
+        } else {
+            gettext("Show album cover")
+        }));
+        self.load(next);
+    }
+
+    fn file_name(&self, bytes: &[u8]) -> String {
+        format!("{}.{}", self.file_stem.borrow(), extension(bytes))
+    }
+
+    pub fn add_actions(self: &Rc<Self>, window: &ApplicationWindow) {
+        window.add_action(&{
+            let action = SimpleAction::new("save_cover", None);
+            let win = window.clone();
+            let this = self.clone();
+            action.connect_activate(move |_, _| this.save(&win));
+            action
+        });
+        window.add_action(&{
+            let action = SimpleAction::new("copy_cover", None);
+            let this = self.clone();
+            action.connect_activate(move |_, _| {
+                let Some(texture) = this.texture.borrow().clone() else {
+                    return;
+                };
+                if let Some(display) = Display::default() {
+                    display.clipboard().set_texture(&texture);
+                }
+            });
+            action
+        });
+        window.add_action(&{
+            let action = SimpleAction::new("open_cover", None);
+            let win = window.clone();
+            let this = self.clone();
+            action.connect_activate(move |_, _| this.open(&win));
+            action
+        });
+        window.add_action(&{
+            let action = SimpleAction::new("switch_cover", None);
+            let this = self.clone();
+            action.connect_activate(move |_, _| this.switch_source());
+            action
+        });
+    }
+
+    fn save(&self, window: &ApplicationWindow) {
+        let Some(bytes) = self.bytes.borrow().clone() else {
+            return;
+        };
+        let dialog = FileDialog::builder()
+            .title(gettext("Save cover"))
+            .initial_name(self.file_name(&bytes).replace('/', "_"))
+            .modal(true)
+            .build();
+        dialog.save(Some(window), None::<&Cancellable>, move |res| {
+            let file = match res {
+                Ok(file) => file,
+                Err(_) => return, // dismissed
+            };
+            if let Err(err) = file.replace_contents(
+                &bytes,
+                None,
+                false,
+                FileCreateFlags::REPLACE_DESTINATION,
+                None::<&Cancellable>,
+            ) {
+                error!("Failed to save cover: {err}");
+            }
+        });
+    }
+
+    /// Write the original to our cache dir and hand it to the default image
+    /// viewer. Not the shared temp dir, where anyone could plant a file or
+    /// symlink under the name first.
+    fn open(&self, window: &ApplicationWindow) {
+        let Some(bytes) = self.bytes.borrow().clone() else {
+            return;
+        };
+        let dir = glib::user_cache_dir().join(APP_ID);
+        let path = dir.join(format!("cover.{}", extension(&bytes)));
+        if let Err(err) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, &bytes)) {
+            error!("Failed to write cover: {err}");
+            return;
+        }
+        FileLauncher::new(Some(&gio::File::for_path(&path))).launch(
+            Some(window),
+            None::<&Cancellable>,
+            |res| {
+                if let Err(err) = res {
+                    error!("Failed to open cover: {err}");
+                }
+            },
+        );
+    }
+}
+
+/// File extension for the image format, from its magic bytes.
+fn extension(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "png"
+    } else if bytes.starts_with(b"GIF8") {
+        "gif"
+    } else if bytes.len() > 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "webp"
+    } else {
+        "jpg"
+    }
+}
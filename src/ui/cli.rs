@@ -0,0 +1,137 @@
+use adw::glib::{self, ExitCode, OptionArg, OptionFlags, VariantDict};
+use adw::gtk::{gio::ApplicationCommandLine, ApplicationWindow};
+use adw::{prelude::*, Application};
+use gettextrs::gettext;
+use std::ops::ControlFlow;
+
+use listenmoe::station::Station;
+
+/// Flag options and the window action each one runs.
+const ACTION_OPTIONS: &[(&str, &str)] = &[
+    ("play", "win.play"),
+    ("pause", "win.pause"),
+    ("toggle", "win.toggle"),
+    ("stop", "win.stop"),
+    ("next-station", "win.next_station"),
+];
+
+fn description(option: &str) -> String {
+    match option {
+        "play" => gettext("Start playing"),
+        "pause" => gettext("Pause playback"),
+        "toggle" => gettext("Play or pause"),
+        "stop" => gettext("Stop playback"),
+        "next-station" => gettext("Switch to the other station"),
+        _ => String::new(),
+    }
+}
+
+pub fn add_options(app: &Application) {
+    app.add_main_option(
+        "station",
+        glib::Char::from(b's'),
+        OptionFlags::NONE,
+        OptionArg::String,
+        &gettext("Play a station (jpop or kpop)"),
+        Some("STATION"),
+    );
+    for (option, _) in ACTION_OPTIONS {
+        app.add_main_option(
+            option,
+            glib::Char::from(0),
+            OptionFlags::NONE,
+            OptionArg::None,
+            &description(option),
+            None,
+        );
+    }
+    app.add_main_option(
+        "verbose",
+        glib::Char::from(b'v'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        &gettext("Log debug messages to the terminal"),
+        None,
+    );
+    app.add_main_option(
+        "now-playing",
+        glib::Char::from(b'n'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        &gettext("Print the current track and exit"),
+        None,
+    );
+}
+
+/// Apply the options that only concern this process, then carry on with the
+/// command line.
+pub fn handle_local_options(_app: &Application, options: &VariantDict) -> ControlFlow<ExitCode> {
+    listenmoe::logging::set_verbose(options.contains("verbose"));
+    ControlFlow::Continue(())
+}
+
+/// Handle the options in the primary instance; a second launch forwards its
+/// arguments here, so they drive the window that is already open.
+pub fn command_line(app: &Application, cmdline: &ApplicationCommandLine) -> ExitCode {
+    let options = cmdline.options_dict();
+    let station = match options.lookup::<String>("station") {
+        Ok(Some(name)) => match Station::from_name(&name) {
+            Some(station) => Some(station),
+            None => {
+                cmdline.printerr_literal(&format!(
+                    "{}\n",
+                    gettext("Unknown station “%s”, use jpop or kpop").replace("%s", &name)
+                ));
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => None,
+    };
+    let actions: Vec<&str> = ACTION_OPTIONS
+        .iter()
+        .filter(|(option, _)| options.contains(option))
+        .map(|(_, action)| *action)
+        .collect();
+
+    if options.contains("now-playing") {
+        let track = main_window(app)
+            .and_then(|window| window.action_state("now_playing"))
+            .and_then(|state| state.get::<String>())
+            .unwrap_or_default();
+        if track.is_empty() {
+            cmdline.printerr_literal(&format!("{}\n", gettext("Nothing is playing")));
+            return ExitCode::FAILURE;
+        }
+        cmdline.print_literal(&format!("{track}\n"));
+        return ExitCode::SUCCESS;
+    }
+
+    // A plain launch shows the window; remote control leaves an open window alone.
+    let window = match main_window(app) {
+        Some(window) if station.is_some() || !actions.is_empty() => window,
+        _ => {
+            app.activate();
+            match main_window(app) {
+                Some(window) => window,
+                None => return ExitCode::FAILURE,
+            }
+        }
+    };
+    if let Some(station) = station {
+        activate(&window, &format!("win.{}", station.name()));
+    }
+    for action in actions {
+        activate(&window, action);
+    }
+    ExitCode::SUCCESS
+}
+
+fn main_window(app: &Application) -> Option<ApplicationWindow> {
+    app.windows()
+        .into_iter()
+        .find_map(|window| window.downcast::<ApplicationWindow>().ok())
+}
+
+fn activate(window: &ApplicationWindow, action: &str) {
+    let _ = adw::prelude::WidgetExt::activate_action(window, action, None::<&glib::Variant>);
+}
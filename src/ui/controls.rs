@@ -1,6 +1,24 @@
 use adw::glib;
-use mpris_server::{Metadata, PlaybackStatus, Player};
-use std::{cell::RefCell, rc::Rc, sync::mpsc};
+use adw::gtk::{gdk::Display, IconLookupFlags, IconTheme, TextDirection};
+use adw::prelude::*;
+use mpris_server::{
+    zbus::{self, fdo, zvariant::ObjectPath},
+    LocalPlayerInterface, LocalPlaylistsInterface, LocalRootInterface, LocalServer, LoopStatus,
+    Metadata, PlaybackRate, PlaybackStatus, Playlist, PlaylistId, PlaylistOrdering,
+    PlaylistsProperty, Property, Time, TrackId, Volume,
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::mpsc,
+    time::Duration,
+};
+
+use listenmoe::meta::TrackInfo;
+use listenmoe::station::Station;
+
+const TRACK_PATH: &str = "/io/github/noobping/listenmoe/track";
+const PLAYLIST_PATH: &str = "/io/github/noobping/listenmoe/playlist";
 
 #[derive(Debug, Clone, Copy)]
 pub enum MediaControlEvent {
@@ -10,112 +28,385 @@ pub enum MediaControlEvent {
     Toggle,
     Next,
     Previous,
+    /// Bring the window to the front.
+    Raise,
+    Quit,
+    /// A station picked from the playlists.
+    Station(Station),
 }
 
 pub struct MediaControls {
-    player: Rc<Player>,
+    server: Rc<LocalServer<Remote>>,
     track_n: Rc<RefCell<u64>>,
 }
 
 impl MediaControls {
     pub fn set_playback(&self, status: PlaybackStatus) {
-        let player = self.player.clone();
+        let server = self.server.clone();
         glib::MainContext::default().spawn_local(async move {
-            let _ = player.set_playback_status(status).await;
+            server.imp().status.set(status);
+            let _ = server
+                .properties_changed([Property::PlaybackStatus(status)])
+                .await;
         });
     }
 
-    pub fn set_metadata(&self, title: &str, artist: &str, album: &str, art_url: Option<&str>) {
-        let player = self.player.clone();
+    pub fn set_metadata(&self, info: &TrackInfo, art_url: Option<&str>) {
+        let server = self.server.clone();
         let track_n = self.track_n.clone();
-        let title = title.to_string();
-        let artist = artist.to_string();
-        let album = album.to_string();
+        let info = info.clone();
         let art_url = art_url.map(str::to_string);
 
         glib::MainContext::default().spawn_local(async move {
             *track_n.borrow_mut() += 1;
 
-            let mut b = Metadata::builder()
-                .title(title)
-                .artist([artist])
-                .album(album);
+            // Songs without an ID still need a unique object path per track.
+            let id = match info.song_id {
+                Some(id) => format!("{TRACK_PATH}/{id}"),
+                None => format!("{TRACK_PATH}/local{}", track_n.borrow()),
+            };
+            let artists = if info.artists.is_empty() {
+                vec![info.artist]
+            } else {
+                info.artists
+            };
+            let mut b = Metadata::builder().title(info.title).artist(artists);
 
+            if let Ok(id) = TrackId::try_from(id) {
+                b = b.trackid(id);
+            }
+            if info.duration_secs > 0 {
+                b = b.length(Time::from_secs(info.duration_secs.into()));
+            }
+            if let Some(album) = info.album {
+                b = b.album(album);
+            }
             if let Some(url) = art_url {
                 b = b.art_url(url);
             }
 
-            let _ = player.set_metadata(b.build()).await;
+            let metadata = b.build();
+            server.imp().metadata.replace(metadata.clone());
+            let _ = server
+                .properties_changed([Property::Metadata(metadata)])
+                .await;
+        });
+    }
+
+    /// Keep `ActivePlaylist` in sync with the playing station.
+    pub fn set_station(&self, station: Station) {
+        let remote = self.server.imp();
+        if remote.station.replace(station) == station {
+            return;
+        }
+        let playlist = remote.playlist(station);
+        let server = self.server.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = server
+                .playlists_properties_changed([PlaylistsProperty::ActivePlaylist(Some(playlist))])
+                .await;
         });
     }
+
+    /// Position within the current track. Clients extrapolate it while playing,
+    /// so this doesn't emit a change signal.
+    pub fn set_position(&self, elapsed: Duration) {
+        self.server
+            .imp()
+            .position
+            .set(Time::from_micros(elapsed.as_micros() as i64));
+    }
+}
+
+/// What the remote sees: the playback state, and every station as a playlist
+/// so media widgets can pick one directly.
+struct Remote {
+    identity: String,
+    desktop_entry: String,
+    icon: String,
+    status: Cell<PlaybackStatus>,
+    metadata: RefCell<Metadata>,
+    position: Cell<Time>,
+    station: Cell<Station>,
+    tx: mpsc::Sender<MediaControlEvent>,
+}
+
+impl Remote {
+    fn send(&self, event: MediaControlEvent) -> fdo::Result<()> {
+        let _ = self.tx.send(event);
+        Ok(())
+    }
+
+    fn playlist(&self, station: Station) -> Playlist {
+        let path = format!("{PLAYLIST_PATH}/{}", station.name());
+        Playlist {
+            id: ObjectPath::try_from(path)
+                .map(Into::into)
+                .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked(PLAYLIST_PATH).into()),
+            name: station.display_name().to_owned(),
+            icon: self.icon.clone(),
+        }
+    }
+}
+
+impl LocalRootInterface for Remote {
+    async fn raise(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Raise)
+    }
+
+    async fn quit(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Quit)
+    }
+
+    async fn can_quit(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn set_fullscreen(&self, _fullscreen: bool) -> zbus::Result<()> {
+        Err(fdo::Error::NotSupported("no fullscreen".to_owned()).into())
+    }
+
+    async fn can_set_fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn can_raise(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn has_track_list(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn identity(&self) -> fdo::Result<String> {
+        Ok(self.identity.clone())
+    }
+
+    async fn desktop_entry(&self) -> fdo::Result<String> {
+        Ok(self.desktop_entry.clone())
+    }
+
+    async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+impl LocalPlayerInterface for Remote {
+    async fn next(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Next)
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Previous)
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Pause)
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Toggle)
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Stop)
+    }
+
+    async fn play(&self) -> fdo::Result<()> {
+        self.send(MediaControlEvent::Play)
+    }
+
+    // A live stream can't seek, loop, shuffle or change rate; `can_seek` and
+    // the fixed rate tell clients so, and the rest is ignored.
+    async fn seek(&self, _offset: Time) -> fdo::Result<()> {
+        Ok(())
+    }
+
+    async fn set_position(&self, _track_id: TrackId, _position: Time) -> fdo::Result<()> {
+        Ok(())
+    }
+
+    async fn open_uri(&self, _uri: String) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("no URIs".to_owned()))
+    }
+
+    async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
+        Ok(self.status.get())
+    }
+
+    async fn loop_status(&self) -> fdo::Result<LoopStatus> {
+        Ok(LoopStatus::None)
+    }
+
+    async fn set_loop_status(&self, _loop_status: LoopStatus) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn set_rate(&self, _rate: PlaybackRate) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn shuffle(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn set_shuffle(&self, _shuffle: bool) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn metadata(&self) -> fdo::Result<Metadata> {
+        Ok(self.metadata.borrow().clone())
+    }
+
+    async fn volume(&self) -> fdo::Result<Volume> {
+        Ok(1.0)
+    }
+
+    async fn set_volume(&self, _volume: Volume) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn position(&self) -> fdo::Result<Time> {
+        Ok(self.position.get())
+    }
+
+    async fn minimum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn maximum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn can_go_next(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_go_previous(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_play(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_pause(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_seek(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn can_control(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl LocalPlaylistsInterface for Remote {
+    async fn activate_playlist(&self, playlist_id: PlaylistId) -> fdo::Result<()> {
+        let station = playlist_id
+            .as_str()
+            .strip_prefix(PLAYLIST_PATH)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .and_then(Station::from_name)
+            .ok_or_else(|| {
+                fdo::Error::InvalidArgs(format!("no playlist {}", playlist_id.as_str()))
+            })?;
+        self.send(MediaControlEvent::Station(station))
+    }
+
+    async fn get_playlists(
+        &self,
+        index: u32,
+        max_count: u32,
+        _order: PlaylistOrdering,
+        reverse_order: bool,
+    ) -> fdo::Result<Vec<Playlist>> {
+        let mut stations = Station::ALL.to_vec();
+        if reverse_order {
+            stations.reverse();
+        }
+        Ok(stations
+            .into_iter()
+            .skip(index as usize)
+            .take(max_count as usize)
+            .map(|station| self.playlist(station))
+            .collect())
+    }
+
+    async fn playlist_count(&self) -> fdo::Result<u32> {
+        Ok(Station::ALL.len() as u32)
+    }
+
+    async fn orderings(&self) -> fdo::Result<Vec<PlaylistOrdering>> {
+        Ok(vec![PlaylistOrdering::UserDefined])
+    }
+
+    async fn active_playlist(&self) -> fdo::Result<Option<Playlist>> {
+        Ok(Some(self.playlist(self.station.get())))
+    }
+}
+
+/// URI of the app icon, for the playlist entries.
+fn icon_uri(icon_name: &str) -> String {
+    let Some(display) = Display::default() else {
+        return String::new();
+    };
+    IconTheme::for_display(&display)
+        .lookup_icon(
+            icon_name,
+            &[],
+            128,
+            1,
+            TextDirection::None,
+            IconLookupFlags::empty(),
+        )
+        .file()
+        .map(|file| file.uri().to_string())
+        .unwrap_or_default()
 }
 
 pub fn build_controls(
     bus_suffix: &str,
     identity: &str,
     desktop_entry: &str,
+    station: Station,
 ) -> Result<(Rc<MediaControls>, mpsc::Receiver<MediaControlEvent>), mpris_server::zbus::Error> {
     let (tx, rx) = mpsc::channel();
+    let remote = Remote {
+        identity: identity.to_owned(),
+        desktop_entry: desktop_entry.to_owned(),
+        icon: icon_uri(desktop_entry),
+        status: Cell::new(PlaybackStatus::Stopped),
+        metadata: RefCell::new(Metadata::new()),
+        position: Cell::new(Time::ZERO),
+        station: Cell::new(station),
+        tx,
+    };
 
-    // Create player (async) on the GLib main context
+    // Create the server (async) on the GLib main context
     let ctx = glib::MainContext::default();
-    let player = ctx.block_on(async {
-        Player::builder(bus_suffix)
-            .identity(identity)
-            .desktop_entry(desktop_entry)
-            .can_control(true)
-            .can_play(true)
-            .can_pause(true)
-            .can_go_next(true)
-            .can_go_previous(true)
-            .build()
-            .await
-    })?;
-
-    // Wire MPRIS calls -> our events
-    {
-        let tx = tx.clone();
-        player.connect_play(move |_| {
-            let _ = tx.send(MediaControlEvent::Play);
-        });
-    }
-    {
-        let tx = tx.clone();
-        player.connect_pause(move |_| {
-            let _ = tx.send(MediaControlEvent::Pause);
-        });
-    }
-    {
-        let tx = tx.clone();
-        player.connect_stop(move |_| {
-            let _ = tx.send(MediaControlEvent::Stop);
-        });
-    }
-    {
-        let tx = tx.clone();
-        player.connect_play_pause(move |_| {
-            let _ = tx.send(MediaControlEvent::Toggle);
-        });
-    }
-    {
-        let tx = tx.clone();
-        player.connect_next(move |_| {
-            let _ = tx.send(MediaControlEvent::Next);
-        });
-    }
-    {
-        let tx = tx.clone();
-        player.connect_previous(move |_| {
-            let _ = tx.send(MediaControlEvent::Previous);
-        });
-    }
+    let server = ctx.block_on(LocalServer::new_with_playlists(bus_suffix, remote))?;
 
     // Run event handler task (required) :contentReference[oaicite:1]{index=1}
-    let player = Rc::new(player);
-    ctx.spawn_local(player.clone().run());
+    let server = Rc::new(server);
+    ctx.spawn_local(server.run());
 
     let controls = Rc::new(MediaControls {
-        player,
+        server,
         track_n: Rc::new(RefCell::new(0)),
     });
 
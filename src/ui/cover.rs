@@ -1,16 +1,9 @@
 use adw::gtk;
 use adw::gtk::gdk::gdk_pixbuf::{InterpType::Bilinear, Pixbuf};
 use adw::gtk::gdk::Display;
-use std::error::Error;
+use serde::{Deserialize, Serialize};
 
-pub fn fetch_cover_bytes_blocking(url: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
-    let resp = reqwest::blocking::get(url)?;
-    if !resp.status().is_success() {
-        return Err(format!("Non-success status: {}", resp.status()).into());
-    }
-    let body = resp.bytes()?;
-    Ok(body.to_vec())
-}
+use super::palette::{contrast_ratio, mix, Palette, Rgb};
 
 pub fn install_css_provider() -> gtk::CssProvider {
     let provider = gtk::CssProvider::new();
@@ -24,7 +17,7 @@ pub fn install_css_provider() -> gtk::CssProvider {
     provider
 }
 
-pub fn avg_rgb_from_pixbuf(pixbuf: &Pixbuf) -> (u8, u8, u8) {
+pub fn palette_from_pixbuf(pixbuf: &Pixbuf) -> Palette {
     let small = pixbuf
         .scale_simple(32, 32, Bilinear)
         .unwrap_or_else(|| pixbuf.clone());
@@ -36,48 +29,95 @@ pub fn avg_rgb_from_pixbuf(pixbuf: &Pixbuf) -> (u8, u8, u8) {
     let has_alpha = small.has_alpha();
     let pixels = unsafe { small.pixels() };
 
-    let mut r_sum: u64 = 0;
-    let mut g_sum: u64 = 0;
-    let mut b_sum: u64 = 0;
-    let mut count: u64 = 0;
-
+    let mut samples = Vec::with_capacity(w * h);
     for y in 0..h {
         let row = &pixels[y * rowstride..(y * rowstride + w * n_channels)];
         for x in 0..w {
             let i = x * n_channels;
-            let r = row[i] as u64;
-            let g = row[i + 1] as u64;
-            let b = row[i + 2] as u64;
-
-            if has_alpha {
-                let a = row[i + 3] as u64;
-                if a < 20 {
-                    continue; // ignore near-transparent
-                }
+            if has_alpha && row[i + 3] < 20 {
+                continue; // ignore near-transparent
             }
+            samples.push((row[i], row[i + 1], row[i + 2]));
+        }
+    }
+    Palette::extract(&samples)
+}
 
-            r_sum += r;
-            g_sum += g;
-            b_sum += b;
-            count += 1;
+/// Colors picked from a cover palette for the tinted window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverColors {
+    pub background: Rgb,
+    pub foreground: Rgb,
+    pub viz: Rgb,
+    /// Whether the background is light, i.e. the foreground is black.
+    pub is_light: bool,
+}
+
+/// WCAG AA for normal text.
+const MIN_TEXT_CONTRAST: f64 = 4.5;
+/// The bars are decoration, but should still stand out from the background.
+const MIN_VIZ_CONTRAST: f64 = 1.5;
+
+const BLACK: Rgb = (0, 0, 0);
+const WHITE: Rgb = (255, 255, 255);
+
+/// Use the dominant color as background and the vibrant one for the bars, nudging
+/// both until they contrast enough with the black or white foreground.
+pub fn choose_colors(palette: &Palette) -> CoverColors {
+    let background = palette.dominant;
+    let is_light = contrast_ratio(BLACK, background) >= contrast_ratio(WHITE, background);
+    let (r, g, b) = if palette.vibrant != palette.dominant {
+        palette.vibrant
+    } else {
+        palette.muted
+    };
+    fit_colors(background, boost_saturation(r, g, b, 1.25), is_light)
+}
+
+/// The colors adjusted to a fixed light or dark style, for when the system style is kept:
+/// the background is lightened or darkened until that style's text reads well on it.
+pub fn for_scheme(colors: &CoverColors, dark: bool) -> CoverColors {
+    if colors.is_light != dark {
+        return *colors;
+    }
+    fit_colors(colors.background, colors.viz, !dark)
+}
+
+fn fit_colors(background: Rgb, candidate: Rgb, is_light: bool) -> CoverColors {
+    let mut background = background;
+    let (foreground, away) = if is_light {
+        (BLACK, WHITE)
+    } else {
+        (WHITE, BLACK)
+    };
+    for _ in 0..10 {
+        if contrast_ratio(foreground, background) >= MIN_TEXT_CONTRAST {
+            break;
         }
+        background = mix(background, away, 0.15);
     }
-    if count == 0 {
-        return (128, 128, 128);
+
+    let mut viz = candidate;
+    for step in 1..=10 {
+        if contrast_ratio(viz, background) >= MIN_VIZ_CONTRAST {
+            break;
+        }
+        viz = mix(candidate, foreground, step as f64 / 10.0);
     }
 
-    (
-        (r_sum / count) as u8,
-        (g_sum / count) as u8,
-        (b_sum / count) as u8,
-    )
+    CoverColors {
+        background,
+        foreground,
+        viz,
+        is_light,
+    }
 }
 
-pub fn apply_color(provider: &gtk::CssProvider, tint: (u8, u8, u8), tint_is_light: bool) {
-    let (r, g, b) = tint;
+pub fn apply_color(provider: &gtk::CssProvider, colors: &CoverColors) {
+    let (r, g, b) = colors.background;
 
     // Foreground for buttons/text
-    let (fr, fg, fb) = if tint_is_light { (0, 0, 0) } else { (255, 255, 255) };
+    let (fr, fg, fb) = colors.foreground;
 
     // slightly dim for backdrop
     let (br, bg, bb) = (
@@ -86,17 +126,8 @@ pub fn apply_color(provider: &gtk::CssProvider, tint: (u8, u8, u8), tint_is_ligh
         (b as f32 * 0.92) as u8,
     );
 
-    // viz bar color derived from the tint, but with contrast
-    let (vr, vg, vb) = if tint_is_light {
-        ((r as f32 * 0.25) as u8, (g as f32 * 0.25) as u8, (b as f32 * 0.25) as u8)
-    } else {
-        (
-            (255.0 - (255.0 - r as f32) * 0.25) as u8,
-            (255.0 - (255.0 - g as f32) * 0.25) as u8,
-            (255.0 - (255.0 - b as f32) * 0.25) as u8,
-        )
-    };
-    let (vr, vg, vb) = boost_saturation(vr, vg, vb, 1.25);
+    // viz bar color from the palette, already checked for contrast
+    let (vr, vg, vb) = colors.viz;
     let css = format!(
         r#"
         .titlebar-tint {{
@@ -135,6 +166,10 @@ pub fn apply_color(provider: &gtk::CssProvider, tint: (u8, u8, u8), tint_is_ligh
             background: rgba({fr} {fg} {fb} / 0.18);
         }}
 
+        progressbar.track-progress > trough > progress {{
+            background: rgb({vr} {vg} {vb});
+        }}
+
         popover.cover-tint > contents {{
             background: rgb({r} {g} {b});
             color: rgb({fr} {fg} {fb});
@@ -146,28 +181,89 @@ pub fn apply_color(provider: &gtk::CssProvider, tint: (u8, u8, u8), tint_is_ligh
         "#
     );
 
-    provider.load_from_data(&css);
+    provider.load_from_string(&css);
 }
 
 pub fn apply_cover_tint_css_clear(provider: &gtk::CssProvider) {
-    provider.load_from_data(
+    provider.load_from_string(
         r#"
         .titlebar-tint { background: transparent; }
         .header-viz { color: @accent_color; }
         .header-viz:backdrop { color: @accent_color; }
         headerbar.viz-transparent { background: transparent; box-shadow: none; }
         headerbar.viz-transparent:backdrop { background: transparent; box-shadow: none; }
+        progressbar.track-progress > trough > progress { background: @accent_color; }
         "#
     );
 }
 
-pub fn is_light_color(r: u8, g: u8, b: u8) -> bool {
-    let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
-    luma > 130.0
-}
-
 pub fn boost_saturation(r: u8, g: u8, b: u8, amount: f32) -> (u8, u8, u8) {
     let gray = (r as f32 + g as f32 + b as f32) / 3.0;
     let boost = |c| (gray + (c as f32 - gray) * amount).clamp(0.0, 255.0) as u8;
     (boost(r), boost(g), boost(b))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(dominant: Rgb, vibrant: Rgb) -> Palette {
+        Palette {
+            dominant,
+            vibrant,
+            muted: dominant,
+        }
+    }
+
+    /// Dark, light, mid-gray and saturated covers.
+    fn palettes() -> Vec<Palette> {
+        vec![
+            palette((15, 15, 25), (200, 40, 60)),
+            palette((245, 240, 225), (40, 120, 220)),
+            palette((119, 119, 119), (119, 119, 119)),
+            palette((128, 128, 128), (160, 90, 200)),
+            palette((200, 30, 40), (250, 200, 30)),
+            palette((0, 0, 255), (0, 255, 0)),
+        ]
+    }
+
+    fn text_contrast(colors: &CoverColors) -> f64 {
+        contrast_ratio(colors.foreground, colors.background)
+    }
+
+    #[test]
+    fn text_is_readable_on_every_cover() {
+        for palette in palettes() {
+            let colors = choose_colors(&palette);
+            assert!(
+                text_contrast(&colors) >= MIN_TEXT_CONTRAST,
+                "{palette:?} gave {colors:?}"
+            );
+            let black = colors.foreground == BLACK;
+            assert_eq!(colors.is_light, black);
+        }
+    }
+
+    #[test]
+    fn text_is_readable_in_both_schemes() {
+        for palette in palettes() {
+            let colors = choose_colors(&palette);
+            for dark in [false, true] {
+                let fitted = for_scheme(&colors, dark);
+                assert_eq!(fitted.is_light, !dark);
+                assert_eq!(fitted.foreground, if dark { WHITE } else { BLACK });
+                assert!(
+                    text_contrast(&fitted) >= MIN_TEXT_CONTRAST,
+                    "{palette:?} with dark {dark} gave {fitted:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_colors_that_already_fit_the_scheme() {
+        let colors = choose_colors(&palette((15, 15, 25), (200, 40, 60)));
+        assert!(!colors.is_light);
+        assert_eq!(for_scheme(&colors, true), colors);
+    }
+}
@@ -0,0 +1,142 @@
+use adw::gtk::{
+    self, gdk::Texture, pango::EllipsizeMode, Align, DrawingArea, Label, Orientation, Picture,
+    ProgressBar,
+};
+use adw::prelude::*;
+use gettextrs::gettext;
+
+use super::progress::TrackPosition;
+use super::viz::{make_bars_visualizer, VizHandle, VizStyle};
+use listenmoe::meta::TrackInfo;
+use listenmoe::station::Station;
+
+const COVER_SIZE: i32 = 280;
+const VIZ_HEIGHT: i32 = 96;
+
+/// Full player layout shown below the compact titlebar when the window is expanded.
+pub struct ExpandedView {
+    root: gtk::Box,
+    cover: Picture,
+    title: Label,
+    artist: Label,
+    album: Label,
+    source: Label,
+    progress: ProgressBar,
+    viz: DrawingArea,
+    viz_handle: VizHandle,
+}
+
+impl ExpandedView {
+    pub fn new(n_bars: usize) -> Self {
+        let cover = Picture::builder()
+            .can_shrink(true)
+            .width_request(COVER_SIZE)
+            .height_request(COVER_SIZE)
+            .halign(Align::Center)
+            .alternative_text(gettext("Cover art"))
+            .build();
+        cover.add_css_class("card");
+
+        let make_label = |css: &str| {
+            let label = Label::builder()
+                .ellipsize(EllipsizeMode::End)
+                .justify(gtk::Justification::Center)
+                .build();
+            label.add_css_class(css);
+            label
+        };
+        let title = make_label("title-2");
+        title.set_wrap(true);
+        title.set_lines(2);
+        let artist = make_label("title-4");
+        let album = make_label("dim-label");
+        let source = make_label("caption");
+
+        let progress = ProgressBar::new();
+        progress.set_margin_top(6);
+        progress.add_css_class("track-progress");
+
+        let (viz, viz_handle) = make_bars_visualizer(n_bars, VIZ_HEIGHT);
+        viz.set_vexpand(false);
+
+        let root = gtk::Box::new(Orientation::Vertical, 6);
+        root.add_css_class("titlebar-tint");
+        root.set_vexpand(true);
+        root.set_valign(Align::Fill);
+        let info = gtk::Box::new(Orientation::Vertical, 6);
+        info.set_margin_top(18);
+        info.set_margin_start(18);
+        info.set_margin_end(18);
+        info.append(&cover);
+        info.append(&title);
+        info.append(&artist);
+        info.append(&album);
+        info.append(&source);
+        info.append(&progress);
+        root.append(&info);
+        root.append(&viz);
+        root.set_visible(false);
+
+        Self {
+            root,
+            cover,
+            title,
+            artist,
+            album,
+            source,
+            progress,
+            viz,
+            viz_handle,
+        }
+    }
+
+    pub fn widget(&self) -> &gtk::Box {
+        &self.root
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.root.is_visible()
+    }
+
+    pub fn set_expanded(&self, expanded: bool) {
+        self.root.set_visible(expanded);
+    }
+
+    pub fn set_track(&self, info: &TrackInfo, station: Station) {
+        self.title.set_text(&info.title);
+        self.artist.set_text(&info.artist);
+        self.album.set_text(info.album.as_deref().unwrap_or(""));
+        self.album.set_visible(info.album.is_some());
+
+        let mut source = format!("LISTEN.moe · {}", station.display_name());
+        if let Some(name) = info.requester.as_deref() {
+            source.push_str(" · ");
+            source.push_str(&gettext("Requested by %s").replace("%s", name));
+        }
+        self.source.set_text(&source);
+    }
+
+    pub fn set_cover(&self, texture: Option<&Texture>) {
+        self.cover.set_paintable(texture);
+    }
+
+    pub fn set_position(&self, pos: Option<&TrackPosition>) {
+        self.progress
+            .set_fraction(pos.map_or(0.0, TrackPosition::fraction));
+        self.progress
+            .set_tooltip_text(pos.map(TrackPosition::label).as_deref());
+    }
+
+    pub fn set_viz_style(&self, style: VizStyle) {
+        self.viz_handle.set_style(style);
+        self.viz.set_visible(style != VizStyle::Off);
+    }
+
+    pub fn set_viz_values(&self, values: &[f32], waveform: &[f32]) {
+        if self.root.is_visible() && self.viz.is_visible() {
+            self.viz_handle.set_values(values);
+            self.viz_handle.set_waveform(waveform);
+            self.viz.queue_draw();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use adw::glib;
+use adw::gtk::gdk::Texture;
+use adw::prelude::*;
+use log::error;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::settings::{Settings, EXPORT_CLEAR, EXPORT_DIR, EXPORT_ENABLED, EXPORT_TEMPLATE};
+use listenmoe::meta::TrackInfo;
+use crate::now_playing;
+
+/// Keeps the now-playing files for streaming overlays up to date.
+pub struct Exporter {
+    settings: Rc<Settings>,
+    track: RefCell<Option<TrackInfo>>,
+    /// Original cover bytes; converted to PNG when written.
+    cover: RefCell<Option<glib::Bytes>>,
+    playing: Cell<bool>,
+}
+
+impl Exporter {
+    pub fn new(settings: &Rc<Settings>) -> Rc<Self> {
+        Rc::new(Self {
+            settings: settings.clone(),
+            track: RefCell::new(None),
+            cover: RefCell::new(None),
+            playing: Cell::new(false),
+        })
+    }
+
+    /// Call when the UI switches to a new track (after the playback lag).
+    pub fn track_changed(&self, info: &TrackInfo) {
+        *self.track.borrow_mut() = Some(info.clone());
+        self.cover.borrow_mut().take();
+        self.write();
+    }
+
+    pub fn cover_loaded(&self, bytes: &glib::Bytes) {
+        *self.cover.borrow_mut() = Some(bytes.clone());
+        self.write();
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        if self.playing.replace(playing) != playing && self.settings.boolean(EXPORT_CLEAR) {
+            self.write();
+        }
+    }
+
+    pub fn settings_changed(&self, key: &str) {
+        match key {
+            EXPORT_ENABLED if !self.settings.boolean(EXPORT_ENABLED) => {
+                // Don't leave a stale song on the overlay
+                if let Some(dir) = export_dir(&self.settings) {
+                    report(now_playing::clear(&dir));
+                }
+            }
+            EXPORT_ENABLED | EXPORT_TEMPLATE | EXPORT_DIR | EXPORT_CLEAR => self.write(),
+            _ => {}
+        }
+    }
+
+    fn write(&self) {
+        if !self.settings.boolean(EXPORT_ENABLED) {
+            return;
+        }
+        let Some(dir) = export_dir(&self.settings) else {
+            return;
+        };
+        let track = self.track.borrow();
+        let info = match track.as_ref() {
+            Some(info) if self.playing.get() || !self.settings.boolean(EXPORT_CLEAR) => info,
+            _ => return report(now_playing::clear(&dir)),
+        };
+        let png = self
+            .cover
+            .borrow()
+            .as_ref()
+            .and_then(|bytes| Texture::from_bytes(bytes).ok())
+            .map(|texture| texture.save_to_png_bytes());
+        let template = self.settings.string(EXPORT_TEMPLATE);
+        report(
+            now_playing::write_track(&dir, &template, info)
+                .and_then(|_| now_playing::write_cover(&dir, png.as_deref())),
+        );
+    }
+}
+
+/// The folder from the settings, or the default one.
+pub fn export_dir(settings: &Settings) -> Option<PathBuf> {
+    let dir = settings.string(EXPORT_DIR);
+    if dir.is_empty() {
+        now_playing::default_dir()
+    } else {
+        Some(PathBuf::from(dir))
+    }
+}
+
+fn report(result: std::io::Result<()>) {
+    if let Err(err) = result {
+        error!("Failed to write now-playing files: {err}");
+    }
+}
@@ -0,0 +1,71 @@
+use adw::{gtk::gio::SimpleAction, prelude::*, Application};
+use std::{cell::RefCell, rc::Rc};
+
+use super::animation::Animation;
+use super::export::Exporter;
+use super::notify::{Notifier, NotifyMode};
+use super::server::LocalServer;
+use super::shortcuts;
+use super::tint::Tinter;
+use super::viz::VizStyle;
+use crate::settings::{
+    Settings, EXPORT_CLEAR, EXPORT_DIR, EXPORT_ENABLED, EXPORT_TEMPLATE, HTTP_PORT, HTTP_SERVER,
+    LOG_FILE, NOTIFICATIONS, SHORTCUTS, TINT_MODE, VISUALIZER_STYLE,
+};
+use listenmoe::logging;
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::Player;
+
+/// Applies preference changes to the running app.
+pub struct LiveSettings {
+    pub app: Application,
+    pub tinter: Rc<Tinter>,
+    pub animation: Rc<Animation>,
+    pub notifier: Rc<Notifier>,
+    pub mode_action: SimpleAction,
+    pub exporter: Rc<Exporter>,
+    pub server: Rc<LocalServer>,
+    pub player: Rc<Player>,
+    pub current_track: Rc<RefCell<Option<TrackInfo>>>,
+}
+
+impl LiveSettings {
+    /// Apply the current settings, then follow their changes.
+    pub fn connect(self, settings: &Rc<Settings>) {
+        for key in [VISUALIZER_STYLE, SHORTCUTS, HTTP_SERVER] {
+            self.changed(settings, key);
+        }
+        let weak_settings = Rc::downgrade(settings);
+        settings.connect_changed(move |key| {
+            if let Some(settings) = weak_settings.upgrade() {
+                self.changed(&settings, key);
+            }
+        });
+    }
+
+    fn changed(&self, settings: &Settings, key: &str) {
+        match key {
+            TINT_MODE => self.tinter.set_mode(settings.tint_mode()),
+            VISUALIZER_STYLE => self.animation.set_style(
+                VizStyle::from_name(&settings.string(VISUALIZER_STYLE)).unwrap_or(VizStyle::Bars),
+            ),
+            NOTIFICATIONS => {
+                if let Some(mode) = NotifyMode::from_name(&settings.string(NOTIFICATIONS)) {
+                    self.notifier.set_mode(mode);
+                    self.mode_action.set_state(&mode.name().to_variant());
+                }
+            }
+            SHORTCUTS => shortcuts::apply(&self.app, &shortcuts::load(settings)),
+            EXPORT_ENABLED | EXPORT_TEMPLATE | EXPORT_DIR | EXPORT_CLEAR => {
+                self.exporter.settings_changed(key)
+            }
+            HTTP_SERVER | HTTP_PORT => {
+                self.server
+                    .update(&self.player, self.current_track.borrow().as_ref());
+                self.animation.set_serving(settings.boolean(HTTP_SERVER));
+            }
+            LOG_FILE => logging::set_file_enabled(settings.boolean(LOG_FILE)),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use adw::glib;
+use adw::gtk::{
+    self,
+    gio::{Cancellable, Menu, MenuItem, SimpleAction},
+    ApplicationWindow, Button, Label, Orientation, PolicyType, ScrolledWindow, TextView,
+    UriLauncher, WrapMode,
+};
+use adw::{prelude::*, Dialog, HeaderBar, ToolbarView};
+use gettextrs::gettext;
+use log::error;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::lookup::{
+    default_links, format_links, load_links, parse_links, save_links, LookupLink,
+};
+use listenmoe::meta::TrackInfo;
+
+/// The "Open in…" submenu, rebuilt for each track from the user's link templates.
+pub struct LookupMenu {
+    menu: Menu,
+    links_section: Menu,
+    links: RefCell<Vec<LookupLink>>,
+    current: RefCell<Option<TrackInfo>>,
+}
+
+impl LookupMenu {
+    pub fn new() -> Rc<Self> {
+        let menu = Menu::new();
+        let links_section = Menu::new();
+        let edit_section = Menu::new();
+        edit_section.append(Some(&gettext("Edit links…")), Some("win.edit_lookup"));
+        menu.append_section(None, &links_section);
+        menu.append_section(None, &edit_section);
+
+        Rc::new(Self {
+            menu,
+            links_section,
+            links: RefCell::new(load_links()),
+            current: RefCell::new(None),
+        })
+    }
+
+    pub fn menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    pub fn track_changed(&self, info: &TrackInfo) {
+        *self.current.borrow_mut() = Some(info.clone());
+        self.rebuild();
+    }
+
+    fn rebuild(&self) {
+        self.links_section.remove_all();
+        let current = self.current.borrow();
+        let Some(info) = current.as_ref() else {
+            return;
+        };
+        for link in self.links.borrow().iter() {
+            let Some(uri) = link.expand(info) else {
+                continue; // e.g. no album for this track
+            };
+            let item = MenuItem::new(Some(&link.label), None);
+            item.set_action_and_target_value(Some("win.open_uri"), Some(&uri.to_variant()));
+            self.links_section.append_item(&item);
+        }
+    }
+
+    pub fn add_actions(self: &Rc<Self>, window: &ApplicationWindow) {
+        window.add_action(&{
+            let action = SimpleAction::new("open_uri", Some(glib::VariantTy::STRING));
+            let win = window.clone();
+            action.connect_activate(move |_, param| {
+                let Some(uri) = param.and_then(|p| p.str()) else {
+                    return;
+                };
+                UriLauncher::new(uri).launch(Some(&win), None::<&Cancellable>, |res| {
+                    if let Err(err) = res {
+                        error!("Failed to open link: {err}");
+                    }
+                });
+            });
+            action
+        });
+        window.add_action(&{
+            let action = SimpleAction::new("edit_lookup", None);
+            let win = window.clone();
+            let this = self.clone();
+            action.connect_activate(move |_, _| this.present_editor(&win));
+            action
+        });
+    }
+
+    fn present_editor(self: &Rc<Self>, window: &ApplicationWindow) {
+        let buffer = gtk::TextBuffer::new(None);
+        buffer.set_text(&format_links(&self.links.borrow()));
+        let text_view = TextView::builder()
+            .buffer(&buffer)
+            .monospace(true)
+            .wrap_mode(WrapMode::WordChar)
+            .top_margin(6)
+            .bottom_margin(6)
+            .left_margin(6)
+            .right_margin(6)
+            .build();
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vexpand(true)
+            .child(&text_view)
+            .build();
+        scroller.add_css_class("card");
+
+        let help = Label::builder()
+            .label(gettext(
+                "One link per line as “Label = URL”. Use {artist}, {title} and {album} as placeholders.",
+            ))
+            .wrap(true)
+            .xalign(0.0)
+            .build();
+        help.add_css_class("dim-label");
+
+        let reset = Button::with_label(&gettext("Reset to defaults"));
+        {
+            let buffer = buffer.clone();
+            reset.connect_clicked(move |_| buffer.set_text(&format_links(&default_links())));
+        }
+        let save = Button::with_label(&gettext("Save"));
+        save.add_css_class("suggested-action");
+
+        let header = HeaderBar::new();
+        header.pack_start(&reset);
+        header.pack_end(&save);
+
+        let content = gtk::Box::new(Orientation::Vertical, 12);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&help);
+        content.append(&scroller);
+
+        let toolbar = ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&content));
+
+        let dialog = Dialog::builder()
+            .title(gettext("Lookup links"))
+            .content_width(520)
+            .content_height(360)
+            .child(&toolbar)
+            .build();
+
+        {
+            let this = self.clone();
+            let dialog = dialog.downgrade();
+            save.connect_clicked(move |_| {
+                let (start, end) = buffer.bounds();
+                let links = parse_links(&buffer.text(&start, &end, false));
+                if let Err(err) = save_links(&links) {
+                    error!("Failed to save lookup links: {err}");
+                }
+                *this.links.borrow_mut() = links;
+                this.rebuild();
+                if let Some(dialog) = dialog.upgrade() {
+                    dialog.close();
+                }
+            });
+        }
+
+        dialog.present(Some(window));
+    }
+}
@@ -0,0 +1,291 @@
+use adw::glib;
+use adw::gtk::{
+    self,
+    gio::{self, Cancellable, FileMonitor, FileMonitorFlags},
+    pango::EllipsizeMode,
+    Align, Label, Orientation, Revealer, RevealerTransitionType,
+};
+use adw::prelude::*;
+use gettextrs::gettext;
+use log::warn;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::lyrics::{default_dir, Lyrics, LyricsLibrary};
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::{Player, PlayerEvent};
+
+/// A finished lookup, with the folders to watch when the library was scanned.
+struct Lookup {
+    id: u64,
+    start: SystemTime,
+    lyrics: Option<Lyrics>,
+    scanned: Option<Vec<PathBuf>>,
+}
+
+/// Expandable pane that shows the previous, current and next lyric line.
+pub struct LyricsPane {
+    revealer: Revealer,
+    prev: Label,
+    current: Label,
+    next: Label,
+    player: Weak<Player>,
+    lyrics: RefCell<Option<(Lyrics, SystemTime)>>,
+    shown: Cell<Option<usize>>,
+    lookup_id: Cell<u64>,
+    lookup_tx: mpsc::Sender<Lookup>,
+    lookup_rx: mpsc::Receiver<Lookup>,
+    /// Lookups sent to a worker and not received yet.
+    pending: Cell<usize>,
+    timer: RefCell<Option<glib::SourceId>>,
+    /// Scanned on the first lookup, and again after the folder changed.
+    library: Arc<Mutex<Option<LyricsLibrary>>>,
+    stale: Arc<AtomicBool>,
+    monitors: RefCell<Vec<FileMonitor>>,
+}
+
+impl LyricsPane {
+    pub fn new(player: &Rc<Player>) -> Rc<Self> {
+        let make_label = |css: &str| {
+            let label = Label::builder()
+                .wrap(true)
+                .justify(gtk::Justification::Center)
+                .ellipsize(EllipsizeMode::End)
+                .lines(2)
+                .build();
+            label.add_css_class(css);
+            label
+        };
+        let prev = make_label("dim-label");
+        let current = make_label("heading");
+        let next = make_label("dim-label");
+
+        let content = gtk::Box::new(Orientation::Vertical, 6);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.set_valign(Align::Center);
+        content.append(&prev);
+        content.append(&current);
+        content.append(&next);
+
+        let revealer = Revealer::builder()
+            .transition_type(RevealerTransitionType::SlideDown)
+            .child(&content)
+            .reveal_child(false)
+            .build();
+
+        let (lookup_tx, lookup_rx) = mpsc::channel();
+        let pane = Rc::new(Self {
+            revealer,
+            prev,
+            current,
+            next,
+            player: Rc::downgrade(player),
+            lyrics: RefCell::new(None),
+            shown: Cell::new(None),
+            lookup_id: Cell::new(0),
+            lookup_tx,
+            lookup_rx,
+            pending: Cell::new(0),
+            timer: RefCell::new(None),
+            library: Arc::new(Mutex::new(None)),
+            stale: Arc::new(AtomicBool::new(false)),
+            monitors: RefCell::new(Vec::new()),
+        });
+        pane.show_message(&gettext("No lyrics"));
+
+        let weak = Rc::downgrade(&pane);
+        player.subscribe(move |event| {
+            if let (PlayerEvent::State(_), Some(pane)) = (event, weak.upgrade()) {
+                pane.update();
+            }
+        });
+
+        pane
+    }
+
+    pub fn widget(&self) -> &Revealer {
+        &self.revealer
+    }
+
+    pub fn set_revealed(self: &Rc<Self>, revealed: bool) {
+        self.revealer.set_reveal_child(revealed);
+        self.update();
+    }
+
+    /// Look up lyrics for the new track on a worker thread.
+    pub fn track_changed(self: &Rc<Self>, info: &TrackInfo) {
+        let id = self.lookup_id.get() + 1;
+        self.lookup_id.set(id);
+        self.set_lyrics(None, info.start_time_utc);
+
+        let Some(dir) = default_dir().filter(|d| d.is_dir()) else {
+            return;
+        };
+        let tx = self.lookup_tx.clone();
+        let library = self.library.clone();
+        let stale = self.stale.clone();
+        let artist = info.artist.clone();
+        let title = info.title.clone();
+        let start = info.start_time_utc;
+        self.pending.set(self.pending.get() + 1);
+        self.update();
+        thread::spawn(move || {
+            // A scan that panicked left no library, or an old one; rescanning fixes either
+            let mut library = library.lock().unwrap_or_else(PoisonError::into_inner);
+            let scanned = match library.as_ref() {
+                Some(library) if library.dir() == dir && !stale.swap(false, Ordering::Relaxed) => {
+                    None
+                }
+                _ => {
+                    let scan = library.insert(LyricsLibrary::scan(&dir));
+                    Some(scan.dirs().to_vec())
+                }
+            };
+            let lyrics = library.as_ref().and_then(|l| l.find(&artist, &title));
+            let _ = tx.send(Lookup {
+                id,
+                start,
+                lyrics,
+                scanned,
+            });
+        });
+    }
+
+    /// Start the timer if a lookup is pending, or if the lyrics are shown while
+    /// playing; it stops itself once neither is true.
+    fn update(self: &Rc<Self>) {
+        if self.timer.borrow().is_some() || !self.wants_timer() {
+            return;
+        }
+        let pane = Rc::downgrade(self);
+        let source = glib::timeout_add_local(Duration::from_millis(200), move || {
+            let Some(pane) = pane.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            pane.receive();
+            if pane.revealer.reveals_child() {
+                pane.tick();
+            }
+            if pane.wants_timer() {
+                glib::ControlFlow::Continue
+            } else {
+                pane.timer.borrow_mut().take();
+                glib::ControlFlow::Break
+            }
+        });
+        *self.timer.borrow_mut() = Some(source);
+    }
+
+    fn wants_timer(&self) -> bool {
+        let playing = self
+            .player
+            .upgrade()
+            .is_some_and(|player| player.state().is_active());
+        self.pending.get() > 0
+            || (playing && self.revealer.reveals_child() && self.lyrics.borrow().is_some())
+    }
+
+    fn receive(&self) {
+        for lookup in self.lookup_rx.try_iter() {
+            self.pending.set(self.pending.get().saturating_sub(1));
+            if let Some(dirs) = lookup.scanned {
+                self.watch(&dirs);
+            }
+            if lookup.id == self.lookup_id.get() {
+                self.set_lyrics(lookup.lyrics, lookup.start);
+            }
+        }
+    }
+
+    /// Scan the library again on the next lookup once anything in `dirs` changes.
+    fn watch(&self, dirs: &[PathBuf]) {
+        let mut monitors = self.monitors.borrow_mut();
+        for monitor in monitors.drain(..) {
+            monitor.cancel();
+        }
+        for dir in dirs {
+            let monitor = match gio::File::for_path(dir)
+                .monitor_directory(FileMonitorFlags::WATCH_MOVES, None::<&Cancellable>)
+            {
+                Ok(monitor) => monitor,
+                Err(err) => {
+                    warn!("Failed to watch {}: {err}", dir.display());
+                    continue;
+                }
+            };
+            let stale = self.stale.clone();
+            monitor.connect_changed(move |_, _, _, _| stale.store(true, Ordering::Relaxed));
+            monitors.push(monitor);
+        }
+    }
+
+    fn set_lyrics(&self, lyrics: Option<Lyrics>, start: SystemTime) {
+        self.shown.set(None);
+        match lyrics {
+            Some(lyrics) => {
+                *self.lyrics.borrow_mut() = Some((lyrics, start));
+                self.show_message("");
+                self.tick();
+            }
+            None => {
+                self.lyrics.borrow_mut().take();
+                self.show_message(&gettext("No lyrics"));
+            }
+        }
+    }
+
+    fn tick(&self) {
+        let lyrics = self.lyrics.borrow();
+        let Some((lyrics, start)) = lyrics.as_ref() else {
+            return;
+        };
+        let Some(player) = self.player.upgrade() else {
+            return;
+        };
+        let pos = player
+            .playback_now()
+            .duration_since(*start)
+            .unwrap_or_default();
+        let index = lyrics.line_at(pos);
+        if index == self.shown.get() && index.is_some() {
+            return;
+        }
+        self.shown.set(index);
+
+        let line = |i: Option<usize>| {
+            i.and_then(|i| lyrics.lines.get(i))
+                .map(|l| l.text.as_str())
+                .unwrap_or("")
+        };
+        match index {
+            Some(i) => {
+                self.prev.set_text(line(i.checked_sub(1)));
+                self.current.set_text(line(Some(i)));
+                self.next.set_text(line(Some(i + 1)));
+            }
+            None => {
+                self.prev.set_text("");
+                self.current.set_text("♪");
+                self.next.set_text(line(Some(0)));
+            }
+        }
+    }
+
+    fn show_message(&self, msg: &str) {
+        self.prev.set_text("");
+        self.current.set_text(msg);
+        self.next.set_text("");
+    }
+}
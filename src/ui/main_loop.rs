@@ -0,0 +1,306 @@
+use adw::{
+    glib,
+    gtk::{
+        accessible::Property,
+        gdk::{gdk_pixbuf::Pixbuf, Texture},
+        gio::{Cancellable, MemoryInputStream, Menu, SimpleAction},
+        AccessibleAnnouncementPriority, ApplicationWindow, Button,
+    },
+    prelude::*,
+    WindowTitle,
+};
+use gettextrs::gettext;
+use log::warn;
+use std::{cell::RefCell, rc::Rc, sync::mpsc, time::Duration};
+
+use super::actions;
+use super::art::{CoverPopover, CoverResult};
+#[cfg(target_os = "linux")]
+use super::controls::{MediaControlEvent, MediaControls};
+use super::cover;
+use super::expanded::ExpandedView;
+use super::export::Exporter;
+use super::lookup::LookupMenu;
+use super::lyrics::LyricsPane;
+use super::notify::Notifier;
+use super::progress::TrackProgress;
+use super::server::LocalServer;
+use super::tint::Tinter;
+#[cfg(target_os = "linux")]
+use super::tray::{Tray, TrayEvent};
+use crate::cover_cache;
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::{Player, PlayerEvent};
+
+const COVER_MAX_SIZE: i32 = 250;
+
+/// Feeds the tracks, covers and remote commands from the worker threads to
+/// everything that shows them, on the GTK main thread.
+pub struct MainLoop {
+    pub window: ApplicationWindow,
+    pub title: WindowTitle,
+    pub cover_button: Button,
+    pub player: Rc<Player>,
+    pub current_track: Rc<RefCell<Option<TrackInfo>>>,
+    pub notifier: Rc<Notifier>,
+    pub exporter: Rc<Exporter>,
+    pub server: Rc<LocalServer>,
+    pub lyrics: Rc<LyricsPane>,
+    pub recent_menu: Menu,
+    pub lookup: Rc<LookupMenu>,
+    pub expanded: Rc<ExpandedView>,
+    pub progress: Rc<TrackProgress>,
+    pub art: Rc<CoverPopover>,
+    pub tinter: Rc<Tinter>,
+    /// Holds the track while playing, for `--now-playing`
+    pub now_playing: SimpleAction,
+    #[cfg(target_os = "linux")]
+    pub controls: Option<Rc<MediaControls>>,
+    #[cfg(target_os = "linux")]
+    pub tray: Option<Rc<Tray>>,
+    pub tracks: mpsc::Receiver<TrackInfo>,
+    pub covers: mpsc::Receiver<CoverResult>,
+    #[cfg(target_os = "linux")]
+    pub media_events: Option<mpsc::Receiver<MediaControlEvent>>,
+    #[cfg(target_os = "linux")]
+    pub tray_events: Option<mpsc::Receiver<TrayEvent>>,
+}
+
+impl MainLoop {
+    /// Follow the player and poll the channels for as long as the app runs.
+    pub fn start(self) {
+        let main_loop = Rc::new(self);
+        {
+            let weak = Rc::downgrade(&main_loop);
+            main_loop.player.subscribe(move |event| {
+                if let Some(main_loop) = weak.upgrade() {
+                    main_loop.player_event(event);
+                }
+            });
+        }
+        {
+            let main_loop = main_loop.clone();
+            glib::timeout_add_local(Duration::from_millis(100), move || {
+                main_loop.poll();
+                glib::ControlFlow::Continue
+            });
+        }
+        glib::timeout_add_local(Duration::from_millis(500), move || {
+            main_loop.update_position();
+            glib::ControlFlow::Continue
+        });
+    }
+
+    /// Everything else that shows the playback state follows the player.
+    fn player_event(&self, event: &PlayerEvent) {
+        if let PlayerEvent::State(state) = event {
+            self.exporter.set_playing(state.is_active());
+            let track = self.current_track.borrow();
+            let track = track.as_ref().filter(|_| state.is_active());
+            self.now_playing.set_state(&now_playing_text(track));
+        }
+        self.server.state_changed(&self.player);
+        #[cfg(target_os = "linux")]
+        if let Some(tray) = &self.tray {
+            match event {
+                PlayerEvent::State(state) => tray.set_playing(state.is_active()),
+                PlayerEvent::Station(station) => tray.set_station(*station),
+            }
+        }
+    }
+
+    fn poll(&self) {
+        self.player.poll();
+
+        #[cfg(target_os = "linux")]
+        if let Some(events) = &self.media_events {
+            for event in events.try_iter() {
+                self.media_event(event);
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(events) = &self.tray_events {
+            for event in events.try_iter() {
+                self.tray_event(event);
+            }
+        }
+        for info in self.tracks.try_iter() {
+            self.track_changed(&info);
+        }
+        for result in self.covers.try_iter() {
+            self.cover_loaded(result);
+        }
+    }
+
+    fn activate(&self, action: &str) {
+        let _ =
+            adw::prelude::WidgetExt::activate_action(&self.window, action, None::<&glib::Variant>);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn media_event(&self, event: MediaControlEvent) {
+        match event {
+            MediaControlEvent::Play => self.activate("win.play"),
+            MediaControlEvent::Pause => self.activate("win.pause"),
+            MediaControlEvent::Stop => self.activate("win.stop"),
+            MediaControlEvent::Toggle => self.activate("win.toggle"),
+            MediaControlEvent::Next => self.activate("win.next_station"),
+            MediaControlEvent::Previous => self.activate("win.prev_station"),
+            MediaControlEvent::Raise => self.window.present(),
+            MediaControlEvent::Quit => self.activate("win.quit"),
+            MediaControlEvent::Station(station) => {
+                self.activate(&format!("win.{}", station.name()))
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn tray_event(&self, event: TrayEvent) {
+        match event {
+            // Left click shows or hides the window, so it can live in the tray
+            TrayEvent::Activate if self.window.is_visible() => self.window.set_visible(false),
+            TrayEvent::Activate => self.window.present(),
+            TrayEvent::Toggle => self.activate("win.toggle"),
+            TrayEvent::Station(station) => self.activate(&format!("win.{}", station.name())),
+            TrayEvent::Quit => self.activate("win.quit"),
+        }
+    }
+
+    fn track_changed(&self, info: &TrackInfo) {
+        self.title.set_title(&info.artist);
+        match info.requester.as_deref() {
+            Some(name) => {
+                let requested = gettext("Requested by %s").replace("%s", name);
+                self.title
+                    .set_subtitle(&format!("{} · {requested}", info.title));
+                self.title.set_tooltip_text(Some(&requested));
+            }
+            None => {
+                self.title.set_subtitle(&info.title);
+                self.title.set_tooltip_text(None);
+            }
+        }
+        *self.current_track.borrow_mut() = Some(info.clone());
+        let station = self.player.station();
+        self.notifier.track_changed(info);
+        self.exporter.track_changed(info);
+        self.server.track_changed(info);
+        self.lyrics.track_changed(info);
+        actions::fill_recent_menu(&self.recent_menu, &self.player.history(), info);
+        self.lookup.track_changed(info);
+        self.expanded.set_track(info, station);
+        self.progress.track_changed(station);
+        self.announce_track(info);
+        self.now_playing.set_state(&now_playing_text(Some(info)));
+
+        #[cfg(target_os = "linux")]
+        {
+            let cover_url = info
+                .album_cover
+                .as_ref()
+                .or(info.artist_image.as_ref())
+                .map(|s| s.as_str());
+            if let Some(controls) = &self.controls {
+                controls.set_metadata(info, cover_url);
+            }
+            if let Some(tray) = &self.tray {
+                tray.set_track(&info.artist, &info.title);
+            }
+        }
+
+        if !self.art.track_changed(info) {
+            self.clear_cover();
+        }
+    }
+
+    fn cover_loaded(&self, result: CoverResult) {
+        let (url, bytes) = match result {
+            Ok((url, _)) if !self.art.is_current(&url) => return, // a previous track's cover
+            Ok((url, bytes)) => (url, glib::Bytes::from_owned(bytes)),
+            Err(err) => {
+                warn!("Failed to load cover bytes: {err}");
+                return self.clear_cover();
+            }
+        };
+        self.notifier.cover_loaded(&bytes);
+        self.exporter.cover_loaded(&bytes);
+        self.server.cover_loaded(&bytes);
+        let stream = MemoryInputStream::from_bytes(&bytes);
+        let pixbuf = match Pixbuf::from_stream_at_scale(
+            &stream,
+            COVER_MAX_SIZE,
+            COVER_MAX_SIZE,
+            true,
+            None::<&Cancellable>,
+        ) {
+            Ok(pixbuf) => pixbuf,
+            Err(err) => {
+                warn!("Failed to decode cover pixbuf: {err}");
+                return self.clear_cover();
+            }
+        };
+        let texture = Texture::for_pixbuf(&pixbuf);
+        // The full player and the cover actions use the original size.
+        let full = Texture::from_bytes(&bytes).unwrap_or_else(|_| texture.clone());
+        self.art.set_cover(&bytes, &texture, &full);
+        self.expanded.set_cover(Some(&full));
+
+        let tint = cover_cache::tint(&url).unwrap_or_else(|| {
+            let palette = cover::palette_from_pixbuf(&pixbuf);
+            let tint = cover::choose_colors(&palette);
+            cover_cache::set_tint(&url, &tint);
+            tint
+        });
+        self.tinter.set_colors(tint);
+    }
+
+    /// Clear the old cover so it doesn't stick around, with its tint.
+    fn clear_cover(&self) {
+        self.art.clear();
+        self.expanded.set_cover(None);
+        self.tinter.clear();
+    }
+
+    fn update_position(&self) {
+        let pos = self.progress.position(
+            self.current_track.borrow().as_ref(),
+            self.player.station(),
+            self.player.playback_now(),
+        );
+        self.progress.set_position(pos.as_ref());
+        if self.expanded.is_expanded() {
+            self.expanded.set_position(pos.as_ref());
+        }
+        #[cfg(target_os = "linux")]
+        if let (Some(controls), Some(pos)) = (&self.controls, pos.as_ref()) {
+            controls.set_position(pos.elapsed);
+        }
+    }
+
+    /// Tell screen readers about the new track, and describe the cover button with it.
+    fn announce_track(&self, info: &TrackInfo) {
+        if info.title.is_empty() {
+            return;
+        }
+        let track = if info.artist.is_empty() {
+            info.title.clone()
+        } else {
+            gettext("%s by %s")
+                .replacen("%s", &info.title, 1)
+                .replacen("%s", &info.artist, 1)
+        };
+        self.cover_button
+            .update_property(&[Property::Description(&track)]);
+        self.window.announce(
+            &gettext("Now playing: %s").replace("%s", &track),
+            AccessibleAnnouncementPriority::Medium,
+        );
+    }
+}
+
+/// The `now_playing` action state: the track while playing, empty otherwise.
+fn now_playing_text(info: Option<&TrackInfo>) -> glib::Variant {
+    info.map(|info| format!("{} - {}", info.artist, info.title))
+        .unwrap_or_default()
+        .to_variant()
+}
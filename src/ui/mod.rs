@@ -1,7 +1,27 @@
 mod actions;
+mod animation;
+mod art;
+mod cli;
 #[cfg(target_os = "linux")]
 mod controls;
 mod cover;
+mod expanded;
+mod export;
+mod live_settings;
+mod lookup;
+mod lyrics;
+mod main_loop;
+mod notify;
+mod palette;
+mod preferences;
+mod progress;
+mod request;
+mod server;
+mod shortcuts;
+mod tint;
+#[cfg(target_os = "linux")]
+mod tray;
 mod viz;
 mod window;
+pub use cli::{add_options, command_line, handle_local_options};
 pub use window::build_ui;
@@ -0,0 +1,190 @@
+use adw::glib;
+use adw::gtk::{
+    gio::{self, BytesIcon, Notification, SimpleAction},
+    ApplicationWindow,
+};
+use adw::{prelude::*, Application};
+use gettextrs::gettext;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::settings::{Settings, NOTIFICATIONS};
+use listenmoe::meta::TrackInfo;
+
+const NOTIFICATION_ID: &str = "now-playing";
+/// Tracks that change faster than this collapse into a single notification.
+const COLLAPSE_DELAY: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyMode {
+    Always,
+    Unfocused,
+    Never,
+}
+
+impl NotifyMode {
+    pub const ALL: [NotifyMode; 3] = [NotifyMode::Always, NotifyMode::Unfocused, NotifyMode::Never];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            NotifyMode::Always => "always",
+            NotifyMode::Unfocused => "unfocused",
+            NotifyMode::Never => "never",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    pub fn display_name(self) -> String {
+        match self {
+            NotifyMode::Always => gettext("Always"),
+            NotifyMode::Unfocused => gettext("When unfocused"),
+            NotifyMode::Never => gettext("Never"),
+        }
+    }
+}
+
+/// Sends a desktop notification for each new track, collapsing quick changes.
+pub struct Notifier {
+    app: Application,
+    window: ApplicationWindow,
+    mode: Cell<NotifyMode>,
+    pending: RefCell<Option<TrackInfo>>,
+    cover: RefCell<Option<glib::Bytes>>,
+    timer: RefCell<Option<glib::SourceId>>,
+}
+
+impl Notifier {
+    pub fn new(app: &Application, window: &ApplicationWindow, mode: NotifyMode) -> Rc<Self> {
+        add_app_actions(app, window);
+        Rc::new(Self {
+            app: app.clone(),
+            window: window.clone(),
+            mode: Cell::new(mode),
+            pending: RefCell::new(None),
+            cover: RefCell::new(None),
+            timer: RefCell::new(None),
+        })
+    }
+
+    pub fn set_mode(&self, mode: NotifyMode) {
+        self.mode.set(mode);
+        if mode == NotifyMode::Never {
+            self.cancel();
+            self.app.withdraw_notification(NOTIFICATION_ID);
+        }
+    }
+
+    /// Call when the UI switches to a new track (after the playback lag).
+    pub fn track_changed(self: &Rc<Self>, info: &TrackInfo) {
+        if self.mode.get() == NotifyMode::Never {
+            return;
+        }
+        *self.pending.borrow_mut() = Some(info.clone());
+        self.cover.borrow_mut().take();
+        self.cancel();
+
+        let this = Rc::downgrade(self);
+        let id = glib::timeout_add_local_once(COLLAPSE_DELAY, move || {
+            if let Some(this) = this.upgrade() {
+                this.timer.borrow_mut().take();
+                this.send_pending();
+            }
+        });
+        *self.timer.borrow_mut() = Some(id);
+    }
+
+    /// Cover bytes for the pending track; used as the notification icon.
+    pub fn cover_loaded(&self, bytes: &glib::Bytes) {
+        *self.cover.borrow_mut() = Some(bytes.clone());
+    }
+
+    pub fn cancel(&self) {
+        if let Some(id) = self.timer.borrow_mut().take() {
+            id.remove();
+        }
+    }
+
+    fn send_pending(&self) {
+        let Some(info) = self.pending.borrow_mut().take() else {
+            return;
+        };
+        let focused = self.window.is_visible() && self.window.is_active();
+        match self.mode.get() {
+            NotifyMode::Never => return,
+            NotifyMode::Unfocused if focused => return,
+            _ => {}
+        }
+
+        let notification = Notification::new(&info.artist);
+        notification.set_body(Some(&info.title));
+        notification.set_category(Some("x-gnome.music"));
+        if let Some(bytes) = self.cover.borrow().as_ref() {
+            notification.set_icon(&BytesIcon::new(bytes));
+        }
+        notification.set_default_action("app.present");
+        notification.add_button(&gettext("Pause"), "app.pause");
+        notification.add_button(&gettext("Next station"), "app.next_station");
+        notification.add_button(&gettext("Copy"), "app.copy");
+
+        self.app
+            .send_notification(Some(NOTIFICATION_ID), &notification);
+    }
+}
+
+/// Notification buttons can only target `app.*` actions; forward them to the window.
+fn add_app_actions(app: &Application, window: &ApplicationWindow) {
+    for name in ["pause", "next_station", "copy"] {
+        let action = SimpleAction::new(name, None);
+        let win = window.clone();
+        action.connect_activate(move |_, _| {
+            let _ = adw::prelude::WidgetExt::activate_action(
+                &win,
+                &format!("win.{name}"),
+                None::<&glib::Variant>,
+            );
+        });
+        app.add_action(&action);
+    }
+
+    let present = SimpleAction::new("present", None);
+    let win = window.clone();
+    present.connect_activate(move |_, _| win.present());
+    app.add_action(&present);
+}
+
+/// Stateful `win.notifications` action backing the radio items in the menu.
+/// Changes go through the settings; the window applies them to the notifier.
+pub fn make_mode_action(settings: &Rc<Settings>) -> SimpleAction {
+    let action = SimpleAction::new_stateful(
+        "notifications",
+        Some(glib::VariantTy::STRING),
+        &settings.string(NOTIFICATIONS).to_variant(),
+    );
+    let settings = settings.clone();
+    action.connect_change_state(move |_, value| {
+        let Some(mode) = value.and_then(|v| v.str()).and_then(NotifyMode::from_name) else {
+            return;
+        };
+        settings.set_string(NOTIFICATIONS, mode.name());
+    });
+    action
+}
+
+pub fn mode_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+    for mode in NotifyMode::ALL {
+        let item = gio::MenuItem::new(Some(&mode.display_name()), None);
+        item.set_action_and_target_value(
+            Some("win.notifications"),
+            Some(&mode.name().to_variant()),
+        );
+        menu.append_item(&item);
+    }
+    menu
+}
@@ -0,0 +1,209 @@
+pub type Rgb = (u8, u8, u8);
+
+/// Number of color boxes median-cut splits the thumbnail into.
+const MAX_SWATCHES: usize = 8;
+
+/// Representative colors of a cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// The most common color.
+    pub dominant: Rgb,
+    /// A saturated, mid-lightness color.
+    pub vibrant: Rgb,
+    /// A desaturated, mid-lightness color.
+    pub muted: Rgb,
+}
+
+impl Palette {
+    /// Median-cut over `pixels`, then pick the swatches closest to each role.
+    pub fn extract(pixels: &[Rgb]) -> Self {
+        let swatches = median_cut(pixels, MAX_SWATCHES);
+        let Some(&(dominant, _)) = swatches.iter().max_by_key(|(_, count)| *count) else {
+            let gray = (128, 128, 128);
+            return Self {
+                dominant: gray,
+                vibrant: gray,
+                muted: gray,
+            };
+        };
+        let max_count = swatches.iter().map(|(_, c)| *c).max().unwrap_or(1) as f64;
+
+        let best = |target_sat: f64, accept: fn(f64) -> bool| {
+            swatches
+                .iter()
+                .filter(|(rgb, _)| accept(saturation_lightness(*rgb).0))
+                .map(|(rgb, count)| {
+                    let (s, l) = saturation_lightness(*rgb);
+                    let score = (1.0 - (s - target_sat).abs()) * 3.0
+                        + (1.0 - (l - 0.5).abs()) * 6.0
+                        + (*count as f64 / max_count);
+                    (*rgb, score)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(rgb, _)| rgb)
+                .unwrap_or(dominant)
+        };
+
+        Self {
+            dominant,
+            vibrant: best(1.0, |s| s >= 0.35),
+            muted: best(0.3, |s| s <= 0.4),
+        }
+    }
+}
+
+/// Split the pixels into up to `max_boxes` boxes along their widest channel.
+/// Returns the average color and pixel count of each box.
+fn median_cut(pixels: &[Rgb], max_boxes: usize) -> Vec<(Rgb, usize)> {
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+    let mut boxes: Vec<Vec<Rgb>> = vec![pixels.to_vec()];
+    while boxes.len() < max_boxes {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range);
+        let Some((i, channel, range)) = widest else {
+            break;
+        };
+        if range == 0 {
+            break; // every box is a single color
+        }
+
+        let mut lower = boxes.swap_remove(i);
+        lower.sort_unstable_by_key(|p| channel_value(*p, channel));
+        let upper = lower.split_off(lower.len() / 2);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|b| (average(b), b.len())).collect()
+}
+
+fn widest_channel(pixels: &[Rgb]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = pixels.iter().map(|p| channel_value(*p, channel));
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn channel_value(p: Rgb, channel: usize) -> u8 {
+    match channel {
+        0 => p.0,
+        1 => p.1,
+        _ => p.2,
+    }
+}
+
+fn average(pixels: &[Rgb]) -> Rgb {
+    let n = pixels.len().max(1) as u64;
+    let (r, g, b) = pixels.iter().fold((0u64, 0u64, 0u64), |acc, p| {
+        (acc.0 + p.0 as u64, acc.1 + p.1 as u64, acc.2 + p.2 as u64)
+    });
+    ((r / n) as u8, (g / n) as u8, (b / n) as u8)
+}
+
+/// HSL saturation and lightness, both 0.0..=1.0.
+fn saturation_lightness((r, g, b): Rgb) -> (f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    let s = if d == 0.0 {
+        0.0
+    } else {
+        d / (1.0 - (2.0 * l - 1.0).abs())
+    };
+    (s.clamp(0.0, 1.0), l)
+}
+
+/// WCAG 2 relative luminance.
+pub fn relative_luminance((r, g, b): Rgb) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG 2 contrast ratio, 1.0..=21.0.
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Linear blend from `a` (t = 0) to `b` (t = 1).
+pub fn mix(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    let t = t.clamp(0.0, 1.0);
+    let m = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64) * t).round() as u8;
+    (m(a.0, b.0), m(a.1, b.1), m(a.2, b.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn black_on_white_is_the_maximum_contrast() {
+        assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio((255, 255, 255), (0, 0, 0)) - 21.0).abs() < 1e-9);
+        assert_eq!(contrast_ratio((90, 30, 200), (90, 30, 200)), 1.0);
+    }
+
+    #[test]
+    fn luminance_spans_black_to_white() {
+        assert_eq!(relative_luminance((0, 0, 0)), 0.0);
+        assert!((relative_luminance((255, 255, 255)) - 1.0).abs() < 1e-9);
+        // Green counts most, blue least
+        assert!(relative_luminance((0, 255, 0)) > relative_luminance((255, 0, 0)));
+        assert!(relative_luminance((255, 0, 0)) > relative_luminance((0, 0, 255)));
+    }
+
+    #[test]
+    fn extracts_gray_from_nothing() {
+        let palette = Palette::extract(&[]);
+        assert_eq!(palette.dominant, (128, 128, 128));
+        assert_eq!(palette.vibrant, (128, 128, 128));
+    }
+
+    #[test]
+    fn extracts_the_only_color() {
+        let palette = Palette::extract(&[(200, 40, 60); 16]);
+        assert_eq!(palette.dominant, (200, 40, 60));
+        assert_eq!(palette.vibrant, (200, 40, 60));
+        assert_eq!(palette.muted, (200, 40, 60));
+    }
+
+    #[test]
+    fn picks_dominant_and_vibrant_colors() {
+        let mut pixels = vec![(30, 30, 35); 60];
+        pixels.extend([(230, 40, 90); 20]);
+        pixels.extend([(140, 140, 140); 20]);
+        let palette = Palette::extract(&pixels);
+        assert_eq!(palette.dominant, (30, 30, 35));
+        assert_eq!(palette.vibrant, (230, 40, 90));
+        assert_eq!(palette.muted, (140, 140, 140));
+    }
+
+    #[test]
+    fn mixes_between_colors() {
+        assert_eq!(mix((0, 0, 0), (255, 255, 255), 0.0), (0, 0, 0));
+        assert_eq!(mix((0, 0, 0), (255, 255, 255), 1.0), (255, 255, 255));
+        assert_eq!(mix((0, 100, 200), (100, 200, 0), 0.5), (50, 150, 100));
+    }
+}
@@ -0,0 +1,437 @@
+use adw::glib;
+use adw::gtk::{
+    gio::{self, Cancellable},
+    Align, ApplicationWindow, Button, EventControllerFocus, FileDialog, FileLauncher, StringList,
+    UriLauncher,
+};
+use adw::{
+    prelude::*, ActionRow, ComboRow, EntryRow, PreferencesDialog, PreferencesGroup,
+    PreferencesPage, SpinRow, SwitchRow,
+};
+use gettextrs::gettext;
+use log::error;
+use std::{fs, rc::Rc};
+
+use super::export::export_dir;
+use super::notify::NotifyMode;
+use super::shortcuts;
+use super::viz::VizStyle;
+use crate::settings::{
+    CloseAction, Settings, TintMode, AUTOPLAY, CLOSE_ACTION, DEFAULT_STATION, EXPORT_CLEAR,
+    EXPORT_DIR, EXPORT_ENABLED, EXPORT_TEMPLATE, HTTP_PORT, HTTP_SERVER, LOG_FILE, NOTIFICATIONS,
+    RESTORE_STATION, TINT_MODE, VISUALIZER_STYLE,
+};
+use crate::cover_cache;
+use listenmoe::logging;
+use listenmoe::station::Station;
+
+pub fn present_preferences(window: &ApplicationWindow, settings: &Rc<Settings>) {
+    let playback = PreferencesGroup::builder()
+        .title(gettext("Playback"))
+        .build();
+    playback.add(&combo_row(
+        settings,
+        DEFAULT_STATION,
+        &gettext("Default station"),
+        Station::ALL
+            .iter()
+            .map(|s| (s.name(), s.display_name().to_owned()))
+            .collect(),
+    ));
+    playback.add(&switch_row(
+        settings,
+        RESTORE_STATION,
+        &gettext("Restore last station"),
+        Some(&gettext(
+            "Start with the station that was playing when the app was closed",
+        )),
+    ));
+    playback.add(&switch_row(
+        settings,
+        AUTOPLAY,
+        &gettext("Play on launch"),
+        None,
+    ));
+
+    let appearance = PreferencesGroup::builder()
+        .title(gettext("Appearance"))
+        .build();
+    let tint_row = combo_row(
+        settings,
+        TINT_MODE,
+        &gettext("Cover colors"),
+        TintMode::ALL
+            .iter()
+            .map(|m| (m.name(), tint_mode_display_name(*m)))
+            .collect(),
+    );
+    tint_row.set_subtitle(&gettext("Tint the window with the colors of the cover"));
+    appearance.add(&tint_row);
+    appearance.add(&combo_row(
+        settings,
+        VISUALIZER_STYLE,
+        &gettext("Visualizer"),
+        VizStyle::ALL
+            .iter()
+            .map(|s| (s.name(), s.display_name()))
+            .collect(),
+    ));
+
+    let behavior = PreferencesGroup::builder()
+        .title(gettext("Behavior"))
+        .build();
+    behavior.add(&combo_row(
+        settings,
+        NOTIFICATIONS,
+        &gettext("Notifications"),
+        NotifyMode::ALL
+            .iter()
+            .map(|m| (m.name(), m.display_name()))
+            .collect(),
+    ));
+    behavior.add(&combo_row(
+        settings,
+        CLOSE_ACTION,
+        &gettext("Close button"),
+        CloseAction::ALL
+            .iter()
+            .map(|a| (a.name(), close_action_display_name(*a)))
+            .collect(),
+    ));
+
+    let export = export_group(window, settings);
+    let server = server_group(window, settings);
+
+    let storage = PreferencesGroup::builder()
+        .title(gettext("Storage"))
+        .build();
+    storage.add(&cover_cache_row());
+
+    let troubleshooting = PreferencesGroup::builder()
+        .title(gettext("Troubleshooting"))
+        .build();
+    let log_dir = logging::log_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+    troubleshooting.add(&switch_row(
+        settings,
+        LOG_FILE,
+        &gettext("Write a log file"),
+        Some(&gettext("Keep a debug log in %s").replace("%s", &log_dir)),
+    ));
+    troubleshooting.add(&debug_log_row());
+
+    let page = PreferencesPage::builder()
+        .title(gettext("General"))
+        .icon_name("preferences-system-symbolic")
+        .build();
+    page.add(&playback);
+    page.add(&appearance);
+    page.add(&behavior);
+    page.add(&export);
+    page.add(&server);
+    page.add(&storage);
+    page.add(&troubleshooting);
+
+    let dialog = PreferencesDialog::new();
+    dialog.add(&page);
+    dialog.add(&shortcuts::preferences_page(&dialog, settings));
+    dialog.present(Some(window));
+}
+
+fn close_action_display_name(action: CloseAction) -> String {
+    match action {
+        CloseAction::Quit => gettext("Quit"),
+        CloseAction::Background => gettext("Keep playing in the background"),
+    }
+}
+
+fn tint_mode_display_name(mode: TintMode) -> String {
+    match mode {
+        TintMode::Cover => gettext("Follow the cover"),
+        TintMode::System => gettext("Keep the system style"),
+        TintMode::Off => gettext("Off"),
+    }
+}
+
+/// Settings for the now-playing files read by streaming software.
+fn export_group(window: &ApplicationWindow, settings: &Rc<Settings>) -> PreferencesGroup {
+    let group = PreferencesGroup::builder()
+        .title(gettext("Now playing files"))
+        .description(gettext(
+            "Show the current song in streaming software such as OBS",
+        ))
+        .build();
+    let enabled = switch_row(settings, EXPORT_ENABLED, &gettext("Write files"), None);
+    group.add(&enabled);
+
+    let template = EntryRow::builder()
+        .title(gettext("Text"))
+        .text(settings.string(EXPORT_TEMPLATE))
+        .show_apply_button(true)
+        .tooltip_text(gettext(
+            "{artist}, {title}, {album}, {requester} and {duration} are replaced",
+        ))
+        .build();
+    {
+        let settings = settings.clone();
+        template.connect_apply(move |row| settings.set_string(EXPORT_TEMPLATE, &row.text()));
+    }
+
+    let folder = ActionRow::builder().title(gettext("Folder")).build();
+    let show_folder = {
+        let folder = folder.clone();
+        move |settings: &Settings| {
+            let dir = export_dir(settings).unwrap_or_default();
+            folder.set_subtitle(&dir.display().to_string());
+        }
+    };
+    show_folder(settings);
+    let open = Button::builder()
+        .icon_name("folder-open-symbolic")
+        .tooltip_text(gettext("Open folder"))
+        .valign(Align::Center)
+        .build();
+    open.add_css_class("flat");
+    {
+        let settings = settings.clone();
+        let window = window.clone();
+        open.connect_clicked(move |_| {
+            let Some(dir) = export_dir(&settings) else {
+                return;
+            };
+            if let Err(err) = fs::create_dir_all(&dir) {
+                error!("Failed to create {}: {err}", dir.display());
+                return;
+            }
+            FileLauncher::new(Some(&gio::File::for_path(&dir))).launch(
+                Some(&window),
+                None::<&Cancellable>,
+                |res| {
+                    if let Err(err) = res {
+                        error!("Failed to open folder: {err}");
+                    }
+                },
+            );
+        });
+    }
+    let choose = Button::builder()
+        .label(gettext("Choose…"))
+        .valign(Align::Center)
+        .build();
+    {
+        let settings = settings.clone();
+        let window = window.clone();
+        choose.connect_clicked(move |_| {
+            let dialog = FileDialog::builder()
+                .title(gettext("Folder for now playing files"))
+                .modal(true)
+                .build();
+            let settings = settings.clone();
+            let show_folder = show_folder.clone();
+            dialog.select_folder(Some(&window), None::<&Cancellable>, move |res| {
+                let Some(path) = res.ok().and_then(|file| file.path()) else {
+                    return; // dismissed
+                };
+                settings.set_string(EXPORT_DIR, &path.to_string_lossy());
+                show_folder(&settings);
+            });
+        });
+    }
+    folder.add_suffix(&open);
+    folder.add_suffix(&choose);
+
+    let clear = switch_row(
+        settings,
+        EXPORT_CLEAR,
+        &gettext("Clear when paused"),
+        Some(&gettext(
+            "Empty the files while playback is paused or stopped",
+        )),
+    );
+
+    for row in [
+        template.upcast_ref::<adw::gtk::Widget>(),
+        folder.upcast_ref(),
+        clear.upcast_ref(),
+    ] {
+        enabled
+            .bind_property("active", row, "sensitive")
+            .sync_create()
+            .build();
+        group.add(row);
+    }
+    group
+}
+
+/// Settings for the local HTTP server and its overlay page.
+fn server_group(window: &ApplicationWindow, settings: &Rc<Settings>) -> PreferencesGroup {
+    let group = PreferencesGroup::builder()
+        .title(gettext("Local server"))
+        .description(gettext(
+            "Share the song, cover and spectrum with browser sources and dashboards on this computer",
+        ))
+        .build();
+    let enabled = switch_row(settings, HTTP_SERVER, &gettext("Serve now playing"), None);
+    group.add(&enabled);
+
+    let port = SpinRow::with_range(1024.0, 65535.0, 1.0);
+    port.set_title(&gettext("Port"));
+    port.set_value(settings.int(HTTP_PORT) as f64);
+
+    let overlay = ActionRow::builder()
+        .title(gettext("Overlay"))
+        .subtitle(overlay_url(settings))
+        .subtitle_selectable(true)
+        .build();
+    let open = Button::builder()
+        .icon_name("web-browser-symbolic")
+        .tooltip_text(gettext("Open in browser"))
+        .valign(Align::Center)
+        .build();
+    open.add_css_class("flat");
+    {
+        let settings = settings.clone();
+        let window = window.clone();
+        open.connect_clicked(move |_| {
+            UriLauncher::new(&overlay_url(&settings)).launch(
+                Some(&window),
+                None::<&Cancellable>,
+                |res| {
+                    if let Err(err) = res {
+                        error!("Failed to open overlay: {err}");
+                    }
+                },
+            );
+        });
+    }
+    overlay.add_suffix(&open);
+    // Every step would restart the server, so only store the port once it's done
+    let apply_port = Button::builder()
+        .icon_name("object-select-symbolic")
+        .tooltip_text(gettext("Apply"))
+        .valign(Align::Center)
+        .visible(false)
+        .build();
+    apply_port.add_css_class("flat");
+    port.add_suffix(&apply_port);
+    let store_port = {
+        let settings = settings.clone();
+        let port = port.clone();
+        let apply_port = apply_port.clone();
+        let overlay = overlay.clone();
+        Rc::new(move || {
+            apply_port.set_visible(false);
+            let value = port.value() as i32;
+            if value == settings.int(HTTP_PORT) {
+                return;
+            }
+            settings.set_int(HTTP_PORT, value);
+            overlay.set_subtitle(&overlay_url(&settings));
+        })
+    };
+    {
+        let settings = settings.clone();
+        let apply_port = apply_port.clone();
+        port.connect_value_notify(move |row| {
+            apply_port.set_visible(row.value() as i32 != settings.int(HTTP_PORT));
+        });
+    }
+    {
+        let store_port = store_port.clone();
+        apply_port.connect_clicked(move |_| store_port());
+    }
+    let focus = EventControllerFocus::new();
+    focus.connect_leave(move |_| store_port());
+    port.add_controller(focus);
+
+    for row in [port.upcast_ref::<adw::gtk::Widget>(), overlay.upcast_ref()] {
+        enabled
+            .bind_property("active", row, "sensitive")
+            .sync_create()
+            .build();
+        group.add(row);
+    }
+    group
+}
+
+fn overlay_url(settings: &Settings) -> String {
+    format!("http://localhost:{}/", settings.int(HTTP_PORT))
+}
+
+fn cover_cache_row() -> ActionRow {
+    let row = ActionRow::builder()
+        .title(gettext("Cover cache"))
+        .subtitle(glib::format_size(cover_cache::size()))
+        .build();
+    let button = Button::builder()
+        .label(gettext("Clear cache"))
+        .valign(Align::Center)
+        .build();
+    let row_clone = row.clone();
+    button.connect_clicked(move |button| {
+        let _ = button.activate_action("win.clear_cache", None);
+        row_clone.set_subtitle(&glib::format_size(cover_cache::size()));
+    });
+    row.add_suffix(&button);
+    row
+}
+
+fn debug_log_row() -> ActionRow {
+    let row = ActionRow::builder()
+        .title(gettext("Debug log"))
+        .subtitle(gettext(
+            "Save the messages of this session for a bug report",
+        ))
+        .build();
+    let button = Button::builder()
+        .label(gettext("Export…"))
+        .action_name("win.export_log")
+        .valign(Align::Center)
+        .build();
+    row.add_suffix(&button);
+    row
+}
+
+fn switch_row(
+    settings: &Rc<Settings>,
+    key: &'static str,
+    title: &str,
+    subtitle: Option<&str>,
+) -> SwitchRow {
+    let row = SwitchRow::builder()
+        .title(title)
+        .active(settings.boolean(key))
+        .build();
+    if let Some(subtitle) = subtitle {
+        row.set_subtitle(subtitle);
+    }
+    let settings = settings.clone();
+    row.connect_active_notify(move |row| settings.set_boolean(key, row.is_active()));
+    row
+}
+
+/// A combo row over `(setting value, label)` options.
+fn combo_row(
+    settings: &Rc<Settings>,
+    key: &'static str,
+    title: &str,
+    options: Vec<(&'static str, String)>,
+) -> ComboRow {
+    let labels: Vec<&str> = options.iter().map(|(_, label)| label.as_str()).collect();
+    let row = ComboRow::builder()
+        .title(title)
+        .model(&StringList::new(&labels))
+        .build();
+    let current = settings.string(key);
+    if let Some(i) = options.iter().position(|(name, _)| *name == current) {
+        row.set_selected(i as u32);
+    }
+    let settings = settings.clone();
+    row.connect_selected_notify(move |row| {
+        if let Some((name, _)) = options.get(row.selected() as usize) {
+            settings.set_string(key, name);
+        }
+    });
+    row
+}
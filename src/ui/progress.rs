@@ -0,0 +1,107 @@
+use adw::gtk::{Align, ProgressBar};
+use adw::prelude::*;
+use std::cell::Cell;
+use std::time::{Duration, SystemTime};
+
+use listenmoe::meta::TrackInfo;
+use listenmoe::station::Station;
+
+/// Where playback is within a track, measured against the lag-adjusted clock.
+#[derive(Debug, Clone, Copy)]
+pub struct TrackPosition {
+    pub elapsed: Duration,
+    /// `None` when the gateway did not report a duration.
+    pub duration: Option<Duration>,
+}
+
+impl TrackPosition {
+    pub fn at(info: &TrackInfo, now: SystemTime) -> Self {
+        let duration =
+            (info.duration_secs > 0).then(|| Duration::from_secs(info.duration_secs.into()));
+        let mut elapsed = now.duration_since(info.start_time_utc).unwrap_or_default();
+        if let Some(d) = duration {
+            // The next track may arrive a little late; don't run past the end.
+            elapsed = elapsed.min(d);
+        }
+        Self { elapsed, duration }
+    }
+
+    pub fn fraction(&self) -> f64 {
+        match self.duration {
+            Some(d) => (self.elapsed.as_secs_f64() / d.as_secs_f64()).clamp(0.0, 1.0),
+            None => 0.0,
+        }
+    }
+
+    /// `1:23 / -2:10`, or just the elapsed time when the duration is unknown.
+    pub fn label(&self) -> String {
+        match self.duration {
+            Some(d) => format!(
+                "{} / -{}",
+                format_time(self.elapsed),
+                format_time(d.saturating_sub(self.elapsed))
+            ),
+            None => format_time(self.elapsed),
+        }
+    }
+}
+
+pub fn format_time(t: Duration) -> String {
+    let secs = t.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Thin progress line along the bottom edge of the header overlay.
+pub struct TrackProgress {
+    bar: ProgressBar,
+    station: Cell<Option<Station>>,
+}
+
+impl TrackProgress {
+    pub fn new() -> Self {
+        let bar = ProgressBar::builder()
+            .valign(Align::End)
+            .hexpand(true)
+            .build();
+        bar.add_css_class("osd");
+        bar.add_css_class("track-progress");
+        Self {
+            bar,
+            station: Cell::new(None),
+        }
+    }
+
+    pub fn widget(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// Remember which station the current track belongs to.
+    pub fn track_changed(&self, station: Station) {
+        self.station.set(Some(station));
+    }
+
+    /// Position of `info` on `station` at `now`, or `None` when the station
+    /// was switched and the new station's track has not arrived yet.
+    pub fn position(
+        &self,
+        info: Option<&TrackInfo>,
+        station: Station,
+        now: SystemTime,
+    ) -> Option<TrackPosition> {
+        if self.station.get() != Some(station) {
+            return None;
+        }
+        info.map(|info| TrackPosition::at(info, now))
+    }
+
+    pub fn set_position(&self, pos: Option<&TrackPosition>) {
+        self.bar
+            .set_fraction(pos.map_or(0.0, TrackPosition::fraction));
+        self.bar
+            .set_tooltip_text(pos.map(TrackPosition::label).as_deref());
+    }
+}
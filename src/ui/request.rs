@@ -0,0 +1,330 @@
+use adw::glib;
+use adw::gtk::{
+    self, Align, ApplicationWindow, Button, Label, ListBox, Orientation, PolicyType,
+    ScrolledWindow, SearchEntry, SelectionMode, Stack,
+};
+use adw::{
+    prelude::*, ActionRow, Dialog, EntryRow, HeaderBar, PasswordEntryRow, ToolbarView,
+};
+use gettextrs::gettext;
+use log::error;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use listenmoe::api::{Api, SearchResult, Session, User};
+use listenmoe::station::Station;
+
+const MIN_QUERY_LEN: usize = 2;
+
+enum Reply {
+    LoggedIn(Session),
+    Quota(User),
+    Results(u64, Vec<SearchResult>),
+    Requested(String),
+    Failed(String),
+}
+
+/// Present the song request dialog. The session outlives the dialog so the
+/// user only has to log in once per run.
+pub fn present_request_dialog(
+    window: &ApplicationWindow,
+    session: &Rc<RefCell<Option<Session>>>,
+    station: Station,
+) {
+    let api = match Api::new() {
+        Ok(api) => api,
+        Err(err) => {
+            error!("Failed to create API client: {err}");
+            return;
+        }
+    };
+    let (tx, rx) = mpsc::channel::<Reply>();
+
+    let stack = Stack::new();
+    let status = Label::builder()
+        .wrap(true)
+        .xalign(0.0)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    status.add_css_class("dim-label");
+
+    // Login page
+    let username = EntryRow::builder().title(gettext("Username")).build();
+    let password = PasswordEntryRow::builder().title(gettext("Password")).build();
+    let login_list = ListBox::new();
+    login_list.set_selection_mode(SelectionMode::None);
+    login_list.add_css_class("boxed-list");
+    login_list.append(&username);
+    login_list.append(&password);
+    let login_button = Button::with_label(&gettext("Log in"));
+    login_button.add_css_class("suggested-action");
+    login_button.add_css_class("pill");
+    login_button.set_halign(Align::Center);
+    let login_error = Label::builder().wrap(true).visible(false).build();
+    login_error.add_css_class("error");
+    let login_page = gtk::Box::new(Orientation::Vertical, 12);
+    login_page.set_margin_top(12);
+    login_page.set_margin_bottom(12);
+    login_page.set_margin_start(12);
+    login_page.set_margin_end(12);
+    login_page.append(
+        &Label::builder()
+            .label(gettext("Log in with your LISTEN.moe account to request songs."))
+            .wrap(true)
+            .build(),
+    );
+    login_page.append(&login_list);
+    login_page.append(&login_button);
+    login_page.append(&login_error);
+    stack.add_named(&login_page, Some("login"));
+
+    // Search page
+    let search = SearchEntry::builder()
+        .placeholder_text(gettext("Search songs, artists or albums"))
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    let results = ListBox::new();
+    results.set_selection_mode(SelectionMode::None);
+    results.add_css_class("boxed-list");
+    results.set_valign(Align::Start);
+    results.set_margin_start(12);
+    results.set_margin_end(12);
+    results.set_margin_bottom(12);
+    let scroller = ScrolledWindow::builder()
+        .hscrollbar_policy(PolicyType::Never)
+        .vexpand(true)
+        .child(&results)
+        .build();
+    let search_page = gtk::Box::new(Orientation::Vertical, 12);
+    search_page.set_margin_top(12);
+    search_page.append(&search);
+    search_page.append(&status);
+    search_page.append(&scroller);
+    stack.add_named(&search_page, Some("search"));
+
+    let toolbar = ToolbarView::new();
+    toolbar.add_top_bar(&HeaderBar::new());
+    toolbar.set_content(Some(&stack));
+
+    let dialog = Dialog::builder()
+        .title(gettext("Request a song"))
+        .content_width(420)
+        .content_height(520)
+        .child(&toolbar)
+        .build();
+
+    if let Some(s) = session.borrow().as_ref() {
+        stack.set_visible_child_name("search");
+        show_quota(&status, &s.user);
+        refresh_quota(&api, s, &tx);
+    } else {
+        stack.set_visible_child_name("login");
+    }
+
+    {
+        let api = api.clone();
+        let tx = tx.clone();
+        let username = username.clone();
+        let password = password.clone();
+        let login_error = login_error.clone();
+        login_button.connect_clicked(move |button| {
+            let user = username.text().to_string();
+            let pass = password.text().to_string();
+            if user.is_empty() || pass.is_empty() {
+                return;
+            }
+            button.set_sensitive(false);
+            login_error.set_visible(false);
+            let api = api.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let _ = tx.send(match api.login(&user, &pass) {
+                    Ok(session) => Reply::LoggedIn(session),
+                    Err(err) => Reply::Failed(err.to_string()),
+                });
+            });
+        });
+    }
+    {
+        let login_button = login_button.clone();
+        password.connect_entry_activated(move |_| login_button.emit_clicked());
+    }
+
+    let generation = Rc::new(Cell::new(0u64));
+    {
+        let api = api.clone();
+        let tx = tx.clone();
+        let session = session.clone();
+        let generation = generation.clone();
+        let results = results.clone();
+        search.connect_search_changed(move |entry| {
+            let query = entry.text().trim().to_string();
+            let my_gen = generation.get() + 1;
+            generation.set(my_gen);
+            if query.chars().count() < MIN_QUERY_LEN {
+                clear_list(&results);
+                return;
+            }
+            let api = api.clone();
+            let tx = tx.clone();
+            let session = session.borrow().clone();
+            thread::spawn(move || {
+                let _ = tx.send(match api.search(session.as_ref(), &query) {
+                    Ok(found) => Reply::Results(my_gen, found),
+                    Err(err) => Reply::Failed(err.to_string()),
+                });
+            });
+        });
+    }
+
+    // Poll worker replies on the GTK main thread while the dialog is alive.
+    {
+        let weak = dialog.downgrade();
+        let session = session.clone();
+        let stack = stack.clone();
+        let status = status.clone();
+        let search = search.clone();
+        glib::timeout_add_local(Duration::from_millis(100), move || {
+            let Some(_dialog) = weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            for reply in rx.try_iter() {
+                match reply {
+                    Reply::LoggedIn(s) => {
+                        show_quota(&status, &s.user);
+                        *session.borrow_mut() = Some(s);
+                        login_button.set_sensitive(true);
+                        stack.set_visible_child_name("search");
+                        search.grab_focus();
+                    }
+                    Reply::Quota(user) => {
+                        show_quota(&status, &user);
+                        if let Some(s) = session.borrow_mut().as_mut() {
+                            s.user = user;
+                        }
+                    }
+                    Reply::Results(id, found) => {
+                        if id != generation.get() {
+                            continue; // stale search
+                        }
+                        clear_list(&results);
+                        for song in found {
+                            results.append(&make_result_row(
+                                song, &api, &session, station, &tx,
+                            ));
+                        }
+                    }
+                    Reply::Requested(title) => {
+                        status.remove_css_class("error");
+                        status.set_text(&gettext("Requested “%s”").replace("%s", &title));
+                        if let Some(s) = session.borrow().as_ref() {
+                            refresh_quota(&api, s, &tx);
+                        }
+                    }
+                    Reply::Failed(msg) => {
+                        login_button.set_sensitive(true);
+                        if stack.visible_child_name().as_deref() == Some("login") {
+                            login_error.set_text(&msg);
+                            login_error.set_visible(true);
+                        } else {
+                            status.add_css_class("error");
+                            status.set_text(&msg);
+                        }
+                    }
+                }
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    dialog.present(Some(window));
+}
+
+fn make_result_row(
+    song: SearchResult,
+    api: &Api,
+    session: &Rc<RefCell<Option<Session>>>,
+    station: Station,
+    tx: &mpsc::Sender<Reply>,
+) -> ActionRow {
+    let mut details = vec![song.artist.clone()];
+    if let Some(album) = song.album.as_ref() {
+        details.push(album.clone());
+    }
+    if song.duration_secs > 0 {
+        details.push(format_duration(song.duration_secs));
+    }
+
+    let row = ActionRow::builder()
+        .title(song.title.as_str())
+        .subtitle(details.join(" · "))
+        .use_markup(false)
+        .build();
+
+    let button = Button::with_label(&gettext("Request"));
+    button.set_valign(Align::Center);
+    button.set_sensitive(session.borrow().is_some());
+    {
+        let api = api.clone();
+        let session = session.clone();
+        let tx = tx.clone();
+        button.connect_clicked(move |button| {
+            let Some(s) = session.borrow().clone() else {
+                return;
+            };
+            button.set_sensitive(false);
+            let api = api.clone();
+            let tx = tx.clone();
+            let id = song.id;
+            let title = song.title.clone();
+            thread::spawn(move || {
+                let _ = tx.send(match api.request_song(&s, id, station) {
+                    Ok(()) => Reply::Requested(title),
+                    Err(err) => Reply::Failed(err.to_string()),
+                });
+            });
+        });
+    }
+    row.add_suffix(&button);
+    row
+}
+
+fn refresh_quota(api: &Api, session: &Session, tx: &mpsc::Sender<Reply>) {
+    let api = api.clone();
+    let session = session.clone();
+    let tx = tx.clone();
+    thread::spawn(move || {
+        let _ = tx.send(match api.user(&session) {
+            Ok(user) => Reply::Quota(user),
+            Err(err) => Reply::Failed(err.to_string()),
+        });
+    });
+}
+
+fn show_quota(status: &Label, user: &User) {
+    status.remove_css_class("error");
+    let text = match user.requests_remaining {
+        Some(n) => gettext("Logged in as %s, %d requests left today")
+            .replacen("%s", user.name(), 1)
+            .replacen("%d", &n.to_string(), 1),
+        None => gettext("Logged in as %s").replace("%s", user.name()),
+    };
+    status.set_text(&text);
+}
+
+fn clear_list(list: &ListBox) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+}
+
+fn format_duration(secs: u32) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,62 @@
+use adw::glib;
+use log::error;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::settings::{Settings, HTTP_PORT, HTTP_SERVER};
+use crate::http_server::HttpServer;
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::Player;
+
+/// The local server for streaming overlays, while it is enabled.
+pub struct LocalServer {
+    settings: Rc<Settings>,
+    server: RefCell<Option<HttpServer>>,
+}
+
+impl LocalServer {
+    pub fn new(settings: &Rc<Settings>) -> Rc<Self> {
+        Rc::new(Self {
+            settings: settings.clone(),
+            server: RefCell::new(None),
+        })
+    }
+
+    /// Start, restart or stop the server to match the settings.
+    pub fn update(&self, player: &Player, track: Option<&TrackInfo>) {
+        // Stop the old one first, so it releases the port
+        self.server.borrow_mut().take();
+        if !self.settings.boolean(HTTP_SERVER) {
+            return;
+        }
+        let port = u16::try_from(self.settings.int(HTTP_PORT)).unwrap_or(8780);
+        let spectrum_bits = player.spectrum_bars();
+        match HttpServer::start(port, player.station(), spectrum_bits) {
+            Ok(server) => {
+                if let Some(info) = track {
+                    server.set_track(info);
+                }
+                server.set_state(&player.state(), player.station());
+                *self.server.borrow_mut() = Some(server);
+            }
+            Err(err) => error!("Failed to start the local server on port {port}: {err}"),
+        }
+    }
+
+    pub fn track_changed(&self, info: &TrackInfo) {
+        if let Some(server) = self.server.borrow().as_ref() {
+            server.set_track(info);
+        }
+    }
+
+    pub fn cover_loaded(&self, bytes: &glib::Bytes) {
+        if let Some(server) = self.server.borrow().as_ref() {
+            server.set_cover(bytes);
+        }
+    }
+
+    pub fn state_changed(&self, player: &Player) {
+        if let Some(server) = self.server.borrow().as_ref() {
+            server.set_state(&player.state(), player.station());
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use adw::gtk::{
+    self,
+    gdk::{Key, ModifierType},
+    glib::Propagation,
+    ApplicationWindow, Button, EventControllerKey, PropagationPhase, ShortcutLabel, ShortcutsGroup,
+    ShortcutsSection, ShortcutsShortcut, ShortcutsWindow,
+};
+use adw::{
+    prelude::*, ActionRow, AlertDialog, Application, PreferencesDialog, PreferencesGroup,
+    PreferencesPage,
+};
+use gettextrs::gettext;
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use crate::settings::{Settings, SHORTCUTS};
+use listenmoe::station::Station;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    Playback,
+    Track,
+    General,
+}
+
+impl Group {
+    const ALL: [Group; 3] = [Group::Playback, Group::Track, Group::General];
+
+    fn title(self) -> String {
+        match self {
+            Group::Playback => gettext("Playback"),
+            Group::Track => gettext("Track"),
+            Group::General => gettext("General"),
+        }
+    }
+}
+
+/// Every rebindable `win.*` action with its default accelerators.
+const ACTIONS: &[(&str, Group, &[&str])] = &[
+    ("toggle", Group::Playback, &["<primary>p", "<primary>s"]),
+    ("play", Group::Playback, &["XF86AudioPlay"]),
+    ("pause", Group::Playback, &["XF86AudioPause"]),
+    ("stop", Group::Playback, &["XF86AudioStop"]),
+    (
+        "next_station",
+        Group::Playback,
+        &["<primary>Right", "XF86AudioNext"],
+    ),
+    (
+        "prev_station",
+        Group::Playback,
+        &["<primary>Left", "XF86AudioPrev"],
+    ),
+    ("jpop", Group::Playback, &["<primary>j"]),
+    ("kpop", Group::Playback, &["<primary>k"]),
+    ("copy", Group::Track, &["<primary>c"]),
+    ("request", Group::Track, &[]),
+    ("lyrics", Group::Track, &["<primary>l"]),
+    ("save_cover", Group::Track, &[]),
+    ("copy_cover", Group::Track, &[]),
+    ("open_cover", Group::Track, &[]),
+    ("switch_cover", Group::Track, &[]),
+    ("edit_lookup", Group::Track, &[]),
+    ("expand", Group::General, &["<primary>e"]),
+    ("cycle_visualizer", Group::General, &["<primary>v"]),
+    ("preferences", Group::General, &["<primary>comma"]),
+    ("shortcuts", Group::General, &["<primary>question"]),
+    ("about", Group::General, &["F1"]),
+    ("clear_cache", Group::General, &[]),
+    ("quit", Group::General, &["<primary>q"]),
+];
+
+fn title(action: &str) -> String {
+    match action {
+        "toggle" => gettext("Play or pause"),
+        "play" => gettext("Play"),
+        "pause" => gettext("Pause"),
+        "stop" => gettext("Stop"),
+        "next_station" => gettext("Next station"),
+        "prev_station" => gettext("Previous station"),
+        "jpop" => gettext("Play %s").replace("%s", Station::Jpop.display_name()),
+        "kpop" => gettext("Play %s").replace("%s", Station::Kpop.display_name()),
+        "copy" => gettext("Copy title & artist"),
+        "request" => gettext("Request a song…"),
+        "lyrics" => gettext("Show lyrics"),
+        "save_cover" => gettext("Save cover…"),
+        "copy_cover" => gettext("Copy image"),
+        "open_cover" => gettext("Open in image viewer"),
+        "switch_cover" => gettext("Show artist image"),
+        "edit_lookup" => gettext("Edit links…"),
+        "expand" => gettext("Expanded view"),
+        "cycle_visualizer" => gettext("Next visualizer style"),
+        "preferences" => gettext("Preferences"),
+        "shortcuts" => gettext("Keyboard Shortcuts"),
+        "about" => gettext("About"),
+        "clear_cache" => gettext("Clear cache"),
+        "quit" => gettext("Quit"),
+        other => other.to_owned(),
+    }
+}
+
+/// Accelerators per action, in `ACTIONS` order.
+pub type Bindings = Vec<(&'static str, Vec<String>)>;
+
+/// The defaults with the user's overrides from the `shortcuts` setting.
+pub fn load(settings: &Settings) -> Bindings {
+    bindings(&settings.string(SHORTCUTS))
+}
+
+/// The defaults with the overrides from a `shortcuts` value.
+fn bindings(stored: &str) -> Bindings {
+    let overrides = parse_overrides(stored);
+    ACTIONS
+        .iter()
+        .map(|(name, _, defaults)| {
+            let accels = overrides
+                .get(*name)
+                .cloned()
+                .unwrap_or_else(|| defaults.iter().map(|a| a.to_string()).collect());
+            (*name, accels)
+        })
+        .collect()
+}
+
+/// Install the bindings as the application's accelerators.
+pub fn apply(app: &Application, bindings: &Bindings) {
+    for (name, accels) in bindings {
+        let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+        app.set_accels_for_action(&format!("win.{name}"), &accels);
+    }
+}
+
+/// Space and Enter toggle playback too, but unlike accelerators they only get
+/// here when the focused widget didn't use them, so typing a space still works.
+pub fn add_play_keys(window: &ApplicationWindow) {
+    let keys = EventControllerKey::new();
+    keys.set_propagation_phase(PropagationPhase::Bubble);
+    keys.connect_key_pressed(|keys, keyval, _, state| {
+        let mods = state & gtk::accelerator_get_default_mod_mask();
+        if !mods.is_empty() || !matches!(keyval, Key::space | Key::Return | Key::KP_Enter) {
+            return Propagation::Proceed;
+        }
+        match keys.widget() {
+            Some(widget) if widget.activate_action("win.toggle", None).is_ok() => Propagation::Stop,
+            _ => Propagation::Proceed,
+        }
+    });
+    window.add_controller(keys);
+}
+
+/// Parse "action=accel accel;action=" into a map; an empty list unbinds the action.
+fn parse_overrides(value: &str) -> HashMap<String, Vec<String>> {
+    value
+        .split(';')
+        .filter_map(|entry| {
+            let (name, accels) = entry.split_once('=')?;
+            let accels = accels.split_whitespace().map(str::to_owned).collect();
+            Some((name.trim().to_owned(), accels))
+        })
+        .filter(|(name, _)| ACTIONS.iter().any(|(n, _, _)| *n == name.as_str()))
+        .collect()
+}
+
+fn store_binding(settings: &Settings, action: &str, accels: Option<Vec<String>>) {
+    let value = with_binding(&settings.string(SHORTCUTS), action, accels);
+    settings.set_string(SHORTCUTS, &value);
+}
+
+/// The `shortcuts` value with `accels` for `action`; `None` or the defaults
+/// drop the override.
+fn with_binding(stored: &str, action: &str, accels: Option<Vec<String>>) -> String {
+    let mut overrides = parse_overrides(stored);
+    let defaults = ACTIONS
+        .iter()
+        .find(|(name, _, _)| *name == action)
+        .map(|(_, _, defaults)| *defaults)
+        .unwrap_or_default();
+    match accels {
+        Some(accels)
+            if accels
+                .iter()
+                .map(String::as_str)
+                .ne(defaults.iter().copied()) =>
+        {
+            overrides.insert(action.to_owned(), accels);
+        }
+        _ => {
+            overrides.remove(action);
+        }
+    }
+    // Keep the stored order stable so the setting doesn't churn.
+    ACTIONS
+        .iter()
+        .filter_map(|(name, _, _)| {
+            let accels = overrides.get(*name)?;
+            Some(format!("{name}={}", accels.join(" ")))
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Canonical form, so "<Ctrl>P" and "<primary>p" compare equal.
+fn normalize(accel: &str) -> Option<(Key, ModifierType)> {
+    gtk::accelerator_parse(accel)
+}
+
+/// The other action already using `accel`, if any.
+fn conflict(bindings: &Bindings, action: &str, accel: &str) -> Option<&'static str> {
+    let wanted = normalize(accel)?;
+    bindings
+        .iter()
+        .filter(|(name, _)| *name != action)
+        .find(|(_, accels)| accels.iter().any(|a| normalize(a) == Some(wanted)))
+        .map(|(name, _)| *name)
+}
+
+/// The help overlay, generated from the current bindings.
+pub fn shortcuts_window(window: &ApplicationWindow, bindings: &Bindings) -> ShortcutsWindow {
+    let section = ShortcutsSection::builder()
+        .section_name("shortcuts")
+        .build();
+    for group in Group::ALL {
+        let shortcuts_group = ShortcutsGroup::builder().title(group.title()).build();
+        for (name, accels) in bindings {
+            let in_group = ACTIONS.iter().any(|(n, g, _)| n == name && *g == group);
+            if !in_group || accels.is_empty() {
+                continue;
+            }
+            let mut accelerator = accels.join(" ");
+            if *name == "toggle" {
+                // See `add_play_keys`
+                accelerator.push_str(" space Return");
+            }
+            shortcuts_group.add_shortcut(
+                &ShortcutsShortcut::builder()
+                    .title(title(name))
+                    .accelerator(accelerator)
+                    .build(),
+            );
+        }
+        section.add_group(&shortcuts_group);
+    }
+    let shortcuts = ShortcutsWindow::builder()
+        .transient_for(window)
+        .modal(true)
+        .build();
+    shortcuts.add_section(&section);
+    shortcuts
+}
+
+/// The "Shortcuts" preferences page for `dialog`: one row per action, activate
+/// a row to rebind it.
+pub fn preferences_page(dialog: &PreferencesDialog, settings: &Rc<Settings>) -> PreferencesPage {
+    let page = PreferencesPage::builder()
+        .title(gettext("Shortcuts"))
+        .icon_name("preferences-desktop-keyboard-shortcuts-symbolic")
+        .build();
+
+    let mut labels = Vec::new();
+    for group in Group::ALL {
+        let pref_group = PreferencesGroup::builder().title(group.title()).build();
+        for (name, _, _) in ACTIONS.iter().filter(|(_, g, _)| *g == group) {
+            let label = ShortcutLabel::builder()
+                .disabled_text(gettext("Disabled"))
+                .valign(gtk::Align::Center)
+                .build();
+            let row = ActionRow::builder()
+                .title(title(name))
+                .activatable(true)
+                .build();
+            row.add_suffix(&label);
+            let settings = settings.clone();
+            let name: &'static str = name;
+            row.connect_activated(move |row| present_editor(row, &settings, name));
+            pref_group.add(&row);
+            labels.push((name, label));
+        }
+        page.add(&pref_group);
+    }
+
+    let reset_group = PreferencesGroup::new();
+    let reset_row = ActionRow::builder()
+        .title(gettext("Reset all shortcuts"))
+        .build();
+    let reset = Button::builder()
+        .label(gettext("Reset"))
+        .valign(gtk::Align::Center)
+        .build();
+    reset.add_css_class("destructive-action");
+    {
+        let settings = settings.clone();
+        reset.connect_clicked(move |_| settings.set_string(SHORTCUTS, ""));
+    }
+    reset_row.add_suffix(&reset);
+    reset_group.add(&reset_row);
+    page.add(&reset_group);
+
+    let refresh = move |settings: &Settings| {
+        let bindings = load(settings);
+        for (name, label) in &labels {
+            let accels = bindings
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, accels)| accels.join(" "))
+                .unwrap_or_default();
+            label.set_accelerator(&accels);
+        }
+    };
+    refresh(settings);
+    let weak_settings = Rc::downgrade(settings);
+    let handler = settings.connect_changed(move |key| {
+        if key != SHORTCUTS {
+            return;
+        }
+        if let Some(settings) = weak_settings.upgrade() {
+            refresh(&settings);
+        }
+    });
+    // The labels only live as long as the dialog
+    let handler = Cell::new(Some(handler));
+    let settings = settings.clone();
+    dialog.connect_closed(move |_| {
+        if let Some(handler) = handler.take() {
+            settings.disconnect(handler);
+        }
+    });
+    page
+}
+
+/// Wait for a key combination and store it for `action`.
+fn present_editor(parent: &ActionRow, settings: &Rc<Settings>, action: &'static str) {
+    let dialog = AlertDialog::builder()
+        .heading(title(action))
+        .body(gettext("Press the new shortcut, or Escape to cancel."))
+        .close_response("cancel")
+        .build();
+    dialog.add_response("cancel", &gettext("Cancel"));
+    dialog.add_response("reset", &gettext("Reset"));
+    dialog.add_response("disable", &gettext("Disable"));
+    {
+        let settings = settings.clone();
+        dialog.connect_response(None, move |_, response| match response {
+            "reset" => store_binding(&settings, action, None),
+            "disable" => store_binding(&settings, action, Some(Vec::new())),
+            _ => {}
+        });
+    }
+
+    let keys = EventControllerKey::new();
+    keys.set_propagation_phase(PropagationPhase::Capture);
+    {
+        let settings = settings.clone();
+        let dialog = dialog.clone();
+        keys.connect_key_pressed(move |_, keyval, _, state| {
+            let mods = state & gtk::accelerator_get_default_mod_mask();
+            if keyval == Key::Escape && mods.is_empty() {
+                return Propagation::Proceed;
+            }
+            let keyval = keyval.to_lower();
+            if !gtk::accelerator_valid(keyval, mods) {
+                return Propagation::Stop; // a lone modifier; wait for the rest
+            }
+            let accel = gtk::accelerator_name(keyval, mods).to_string();
+            if let Some(other) = conflict(&load(&settings), action, &accel) {
+                let label = gtk::accelerator_get_label(keyval, mods);
+                dialog.set_body(
+                    &gettext("%s is already used by “%s”. Press another shortcut.")
+                        .replacen("%s", &label, 1)
+                        .replacen("%s", &title(other), 1),
+                );
+                return Propagation::Stop;
+            }
+            store_binding(&settings, action, Some(vec![accel]));
+            dialog.close();
+            Propagation::Stop
+        });
+    }
+    dialog.add_controller(keys);
+    dialog.present(Some(parent));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accels(bindings: &Bindings, action: &str) -> Vec<String> {
+        bindings
+            .iter()
+            .find(|(name, _)| *name == action)
+            .map(|(_, accels)| accels.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trips_an_override() {
+        let stored = with_binding("", "copy", Some(vec!["<primary>y".into()]));
+        assert_eq!(stored, "copy=<primary>y");
+        assert_eq!(accels(&bindings(&stored), "copy"), ["<primary>y"]);
+        assert_eq!(accels(&bindings(&stored), "quit"), ["<primary>q"]);
+    }
+
+    #[test]
+    fn storing_the_default_removes_the_override() {
+        let stored = with_binding("copy=<primary>y", "copy", Some(vec!["<primary>c".into()]));
+        assert_eq!(stored, "");
+        assert_eq!(
+            with_binding("copy=<primary>y;quit=F10", "copy", None),
+            "quit=F10"
+        );
+    }
+
+    #[test]
+    fn an_empty_list_unbinds() {
+        let stored = with_binding("", "quit", Some(Vec::new()));
+        assert_eq!(stored, "quit=");
+        assert!(accels(&bindings(&stored), "quit").is_empty());
+    }
+
+    #[test]
+    fn drops_unknown_actions() {
+        let overrides = parse_overrides("bogus=F2;quit=F10;=F3;copy");
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides["quit"], ["F10"]);
+        assert_eq!(
+            with_binding("bogus=F2", "quit", Some(vec!["F10".into()])),
+            "quit=F10"
+        );
+    }
+
+    #[test]
+    fn keeps_the_stored_order_stable() {
+        let stored = with_binding("quit=F10", "toggle", Some(vec!["F5".into()]));
+        assert_eq!(stored, "toggle=F5;quit=F10");
+    }
+
+    #[test]
+    #[ignore = "parsing accelerators needs GTK, which needs a display"]
+    fn finds_conflicts_across_spellings() {
+        gtk::init().unwrap();
+        let bindings = bindings("");
+        assert_eq!(conflict(&bindings, "copy", "<Ctrl>P"), Some("toggle"));
+        assert_eq!(conflict(&bindings, "copy", "<primary>p"), Some("toggle"));
+        assert_eq!(conflict(&bindings, "toggle", "<Ctrl>P"), None);
+        assert_eq!(conflict(&bindings, "copy", "<Ctrl><Shift>P"), None);
+    }
+}
@@ -0,0 +1,102 @@
+use adw::{gtk, StyleManager};
+use std::{cell::Cell, rc::Rc};
+
+use super::cover::{self, CoverColors};
+use crate::settings::TintMode;
+
+/// Tints the window with the colors of the current cover.
+pub struct Tinter {
+    style_manager: StyleManager,
+    css_provider: gtk::CssProvider,
+    mode: Cell<TintMode>,
+    /// Colors of the last cover, kept so tinting can be re-enabled live.
+    colors: Cell<Option<CoverColors>>,
+}
+
+impl Tinter {
+    pub fn new(mode: TintMode) -> Rc<Self> {
+        let style_manager = StyleManager::default();
+        style_manager.set_color_scheme(adw::ColorScheme::Default);
+        let tinter = Rc::new(Self {
+            style_manager: style_manager.clone(),
+            css_provider: cover::install_css_provider(),
+            mode: Cell::new(mode),
+            colors: Cell::new(None),
+        });
+        {
+            let tinter = Rc::downgrade(&tinter);
+            style_manager.connect_high_contrast_notify(move |_| {
+                if let Some(tinter) = tinter.upgrade() {
+                    tinter.refresh();
+                }
+            });
+        }
+        {
+            let tinter = Rc::downgrade(&tinter);
+            // Only the system style can change under us; the forced ones are ours.
+            style_manager.connect_dark_notify(move |_| {
+                if let Some(tinter) = tinter.upgrade() {
+                    if tinter.mode() == TintMode::System {
+                        tinter.refresh();
+                    }
+                }
+            });
+        }
+        tinter
+    }
+
+    pub fn set_mode(&self, mode: TintMode) {
+        self.mode.set(mode);
+        self.refresh();
+    }
+
+    pub fn set_colors(&self, colors: CoverColors) {
+        self.colors.set(Some(colors));
+        self.refresh();
+    }
+
+    pub fn clear(&self) {
+        self.colors.set(None);
+        self.refresh();
+    }
+
+    /// The tint mode setting, or off with high contrast; the tint would override the high contrast colors.
+    fn mode(&self) -> TintMode {
+        if self.style_manager.is_high_contrast() {
+            TintMode::Off
+        } else {
+            self.mode.get()
+        }
+    }
+
+    fn refresh(&self) {
+        let Some(colors) = self.colors.get() else {
+            return self.reset();
+        };
+        match self.mode() {
+            TintMode::Cover => {
+                self.style_manager.set_color_scheme(if colors.is_light {
+                    adw::ColorScheme::ForceLight
+                } else {
+                    adw::ColorScheme::ForceDark
+                });
+                cover::apply_color(&self.css_provider, &colors);
+            }
+            TintMode::System => {
+                self.style_manager
+                    .set_color_scheme(adw::ColorScheme::Default);
+                cover::apply_color(
+                    &self.css_provider,
+                    &cover::for_scheme(&colors, self.style_manager.is_dark()),
+                );
+            }
+            TintMode::Off => self.reset(),
+        }
+    }
+
+    fn reset(&self) {
+        self.style_manager
+            .set_color_scheme(adw::ColorScheme::Default);
+        cover::apply_cover_tint_css_clear(&self.css_provider);
+    }
+}
@@ -0,0 +1,403 @@
+use adw::glib;
+use gettextrs::gettext;
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+};
+use zbus::{
+    interface,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Structure, Value},
+    Connection,
+};
+
+use listenmoe::station::Station;
+
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const ITEM_IFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const MENU_IFACE: &str = "com.canonical.dbusmenu";
+
+// dbusmenu item ids; 0 is the root
+const ID_TRACK: i32 = 1;
+const ID_TOGGLE: i32 = 2;
+const ID_QUIT: i32 = 3;
+const ID_SEPARATOR: i32 = 4;
+const ID_SEPARATOR_2: i32 = 5;
+const ID_STATIONS: i32 = 10;
+
+/// Icon name, icon pixmaps, title and body, as the `ToolTip` property wants them.
+type ToolTip = (String, Vec<(i32, i32, Vec<u8>)>, String, String);
+
+#[derive(Debug, Clone, Copy)]
+pub enum TrayEvent {
+    /// Left click on the icon.
+    Activate,
+    Toggle,
+    Station(Station),
+    Quit,
+}
+
+#[derive(Debug)]
+struct TrayState {
+    icon_name: String,
+    title: String,
+    artist: String,
+    track: String,
+    playing: bool,
+    station: Station,
+    revision: u32,
+}
+
+type Props = HashMap<String, OwnedValue>;
+
+/// A StatusNotifierItem with a dbusmenu, for panels that show tray icons.
+pub struct Tray {
+    conn: Connection,
+    state: Arc<Mutex<TrayState>>,
+}
+
+impl Tray {
+    pub fn set_track(&self, artist: &str, title: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.artist = artist.to_owned();
+            state.track = title.to_owned();
+        }
+        self.emit(ITEM_PATH, ITEM_IFACE, "NewToolTip");
+        self.layout_updated();
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.playing == playing {
+                return;
+            }
+            state.playing = playing;
+        }
+        self.layout_updated();
+    }
+
+    pub fn set_station(&self, station: Station) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.station == station {
+                return;
+            }
+            state.station = station;
+        }
+        self.layout_updated();
+    }
+
+    fn layout_updated(&self) {
+        let revision = {
+            let mut state = self.state.lock().unwrap();
+            state.revision += 1;
+            state.revision
+        };
+        let conn = self.conn.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = conn
+                .emit_signal(
+                    None::<&str>,
+                    MENU_PATH,
+                    MENU_IFACE,
+                    "LayoutUpdated",
+                    &(revision, 0i32),
+                )
+                .await;
+        });
+    }
+
+    fn emit(&self, path: &'static str, iface: &'static str, signal: &'static str) {
+        let conn = self.conn.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = conn
+                .emit_signal(None::<&str>, path, iface, signal, &())
+                .await;
+        });
+    }
+}
+
+struct StatusNotifierItem {
+    state: Arc<Mutex<TrayState>>,
+    tx: mpsc::Sender<TrayEvent>,
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl StatusNotifierItem {
+    fn activate(&self, _x: i32, _y: i32) {
+        let _ = self.tx.send(TrayEvent::Activate);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {
+        let _ = self.tx.send(TrayEvent::Toggle);
+    }
+
+    fn context_menu(&self, _x: i32, _y: i32) {
+        // The host shows the menu from the `Menu` property.
+    }
+
+    fn scroll(&self, _delta: i32, _orientation: String) {}
+
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.state.lock().unwrap().icon_name.clone()
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> String {
+        self.state.lock().unwrap().title.clone()
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> String {
+        self.state.lock().unwrap().icon_name.clone()
+    }
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> ToolTip {
+        let state = self.state.lock().unwrap();
+        let (title, body) = if state.artist.is_empty() && state.track.is_empty() {
+            (state.title.clone(), String::new())
+        } else {
+            (state.artist.clone(), state.track.clone())
+        };
+        (state.icon_name.clone(), Vec::new(), title, body)
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(MENU_PATH).into()
+    }
+}
+
+struct DbusMenu {
+    state: Arc<Mutex<TrayState>>,
+    tx: mpsc::Sender<TrayEvent>,
+}
+
+impl DbusMenu {
+    fn items(&self) -> Vec<(i32, Props)> {
+        let state = self.state.lock().unwrap();
+        let track = if state.track.is_empty() {
+            state.title.clone()
+        } else {
+            format!("{} — {}", state.artist, state.track)
+        };
+        let toggle = if state.playing {
+            gettext("Pause")
+        } else {
+            gettext("Play")
+        };
+
+        let mut items = vec![
+            (
+                ID_TRACK,
+                props(vec![
+                    ("label", Value::from(track)),
+                    ("enabled", Value::from(false)),
+                ]),
+            ),
+            (ID_SEPARATOR, separator()),
+            (ID_TOGGLE, props(vec![("label", Value::from(toggle))])),
+        ];
+        for (i, station) in Station::ALL.into_iter().enumerate() {
+            let label = gettext("Play %s").replace("%s", station.display_name());
+            items.push((
+                ID_STATIONS + i as i32,
+                props(vec![
+                    ("label", Value::from(label)),
+                    ("toggle-type", Value::from("radio")),
+                    (
+                        "toggle-state",
+                        Value::from(i32::from(station == state.station)),
+                    ),
+                ]),
+            ));
+        }
+        items.push((ID_SEPARATOR_2, separator()));
+        items.push((
+            ID_QUIT,
+            props(vec![("label", Value::from(gettext("Quit")))]),
+        ));
+        items
+    }
+
+    fn clicked(&self, id: i32) {
+        let event = match id {
+            ID_TOGGLE => TrayEvent::Toggle,
+            ID_QUIT => TrayEvent::Quit,
+            id if id >= ID_STATIONS => match Station::ALL.get((id - ID_STATIONS) as usize) {
+                Some(station) => TrayEvent::Station(*station),
+                None => return,
+            },
+            _ => return,
+        };
+        let _ = self.tx.send(event);
+    }
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl DbusMenu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        _recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> (u32, (i32, Props, Vec<OwnedValue>)) {
+        let revision = self.state.lock().unwrap().revision;
+        // The menu is flat, so only the root has children
+        let children = if parent_id == 0 {
+            self.items()
+                .into_iter()
+                .filter_map(|(id, props)| {
+                    let child = Structure::from((id, props, Vec::<OwnedValue>::new()));
+                    Value::from(child).try_to_owned().ok()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let root = props(vec![("children-display", Value::from("submenu"))]);
+        (revision, (parent_id, root, children))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, Props)> {
+        self.items()
+            .into_iter()
+            .filter(|(id, _)| ids.is_empty() || ids.contains(id))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: String) -> zbus::fdo::Result<OwnedValue> {
+        self.items()
+            .into_iter()
+            .find(|(item, _)| *item == id)
+            .and_then(|(_, mut props)| props.remove(&name))
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no property {name} on {id}")))
+    }
+
+    fn event(&self, id: i32, event_id: String, _data: OwnedValue, _timestamp: u32) {
+        if event_id == "clicked" {
+            self.clicked(id);
+        }
+    }
+
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        for (id, event_id, _, _) in events {
+            if event_id == "clicked" {
+                self.clicked(id);
+            }
+        }
+        Vec::new()
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+fn separator() -> Props {
+    props(vec![("type", Value::from("separator"))])
+}
+
+fn props(pairs: Vec<(&str, Value<'_>)>) -> Props {
+    pairs
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_owned(), value.try_to_owned().ok()?)))
+        .collect()
+}
+
+/// Export the tray icon and register it with the panel's StatusNotifierWatcher.
+pub fn build_tray(
+    icon_name: &str,
+    title: &str,
+    station: Station,
+) -> Result<(Rc<Tray>, mpsc::Receiver<TrayEvent>), zbus::Error> {
+    let (tx, rx) = mpsc::channel();
+    let state = Arc::new(Mutex::new(TrayState {
+        icon_name: icon_name.to_owned(),
+        title: title.to_owned(),
+        artist: String::new(),
+        track: String::new(),
+        playing: false,
+        station,
+        revision: 1,
+    }));
+    let item = StatusNotifierItem {
+        state: state.clone(),
+        tx: tx.clone(),
+    };
+    let menu = DbusMenu {
+        state: state.clone(),
+        tx,
+    };
+
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let conn = glib::MainContext::default().block_on(async {
+        let conn = zbus::connection::Builder::session()?
+            .name(name.as_str())?
+            .serve_at(ITEM_PATH, item)?
+            .serve_at(MENU_PATH, menu)?
+            .build()
+            .await?;
+        conn.call_method(
+            Some(WATCHER_NAME),
+            WATCHER_PATH,
+            Some(WATCHER_NAME),
+            "RegisterStatusNotifierItem",
+            &(name.as_str(),),
+        )
+        .await?;
+        Ok::<_, zbus::Error>(conn)
+    })?;
+
+    Ok((Rc::new(Tray { conn, state }), rx))
+}
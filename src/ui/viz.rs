@@ -1,11 +1,76 @@
 use adw::gtk;
+use gettextrs::gettext;
 use gtk::prelude::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
+/// How the visualizer draws the spectrum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VizStyle {
+    /// Bottom-anchored filled bars.
+    Bars,
+    /// Bars growing out from the vertical center.
+    Mirrored,
+    /// A filled line through the bar tops.
+    Line,
+    /// The time-domain waveform.
+    Oscilloscope,
+    /// Bars with slowly falling peak markers.
+    PeakHold,
+    Off,
+}
+
+impl VizStyle {
+    pub const ALL: [VizStyle; 6] = [
+        VizStyle::Bars,
+        VizStyle::Mirrored,
+        VizStyle::Line,
+        VizStyle::Oscilloscope,
+        VizStyle::PeakHold,
+        VizStyle::Off,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            VizStyle::Bars => "bars",
+            VizStyle::Mirrored => "mirrored",
+            VizStyle::Line => "line",
+            VizStyle::Oscilloscope => "oscilloscope",
+            VizStyle::PeakHold => "peak-hold",
+            VizStyle::Off => "off",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn display_name(self) -> String {
+        match self {
+            VizStyle::Bars => gettext("Bars"),
+            VizStyle::Mirrored => gettext("Mirrored bars"),
+            VizStyle::Line => gettext("Line"),
+            VizStyle::Oscilloscope => gettext("Oscilloscope"),
+            VizStyle::PeakHold => gettext("Peak hold"),
+            VizStyle::Off => gettext("Off"),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// How far a peak marker falls per frame.
+const PEAK_FALL: f32 = 0.01;
+
 #[derive(Clone)]
 pub struct VizHandle {
     values: Rc<RefCell<Vec<f32>>>, // 0.0..=1.0
+    peaks: Rc<RefCell<Vec<f32>>>,
+    waveform: Rc<RefCell<Vec<f32>>>, // -1.0..=1.0
+    style: Rc<Cell<VizStyle>>,
 }
 
 impl VizHandle {
@@ -13,6 +78,26 @@ impl VizHandle {
         let mut v = self.values.borrow_mut();
         v.clear();
         v.extend(new_vals.iter().map(|x| x.clamp(0.0, 1.0)));
+
+        let mut peaks = self.peaks.borrow_mut();
+        peaks.resize(v.len(), 0.0);
+        for (peak, value) in peaks.iter_mut().zip(v.iter()) {
+            *peak = (*peak - PEAK_FALL).max(*value);
+        }
+    }
+
+    pub fn set_waveform(&self, samples: &[f32]) {
+        let mut w = self.waveform.borrow_mut();
+        w.clear();
+        w.extend(samples.iter().map(|x| x.clamp(-1.0, 1.0)));
+    }
+
+    pub fn style(&self) -> VizStyle {
+        self.style.get()
+    }
+
+    pub fn set_style(&self, style: VizStyle) {
+        self.style.set(style);
     }
 }
 
@@ -22,15 +107,24 @@ pub fn make_bars_visualizer(n_bars: usize, height: i32) -> (gtk::DrawingArea, Vi
     let values = Rc::new(RefCell::new(vec![0.0_f32; n_bars.max(1)]));
     let handle = VizHandle {
         values: values.clone(),
+        peaks: Rc::new(RefCell::new(vec![0.0_f32; n_bars.max(1)])),
+        waveform: Rc::new(RefCell::new(Vec::new())),
+        style: Rc::new(Cell::new(VizStyle::Bars)),
     };
 
-    let area = gtk::DrawingArea::new();
+    let area = gtk::DrawingArea::builder()
+        .accessible_role(gtk::AccessibleRole::Img)
+        .build();
+    area.update_property(&[gtk::accessible::Property::Label(&gettext(
+        "Audio visualizer",
+    ))]);
     area.set_hexpand(true);
     area.set_vexpand(true);
     area.set_content_height(height);
     area.add_css_class("header-viz");
 
     let area_clone = area.clone();
+    let draw_handle = handle.clone();
     area.set_draw_func(move |_, cr, w, h| {
         let w = w as f64;
         let h = h as f64;
@@ -53,23 +147,92 @@ pub fn make_bars_visualizer(n_bars: usize, height: i32) -> (gtk::DrawingArea, Vi
         let n = vals.len().max(1) as f64;
         let bar_w = (w / n).max(1.0);
 
-        for (i, v) in vals.iter().enumerate() {
-            let i = i as f64;
-            let x = i * bar_w;
-
-            let bh = (*v as f64) * (h * 0.85);
-            let y = h - bh;
-
-            // Slightly wider than computed bar_w to avoid gaps at small widths.
-            cr.rectangle(x, y, (bar_w * 2.0).max(1.0), bh.max(1.0));
+        match draw_handle.style() {
+            VizStyle::Bars | VizStyle::PeakHold => {
+                for (i, v) in vals.iter().enumerate() {
+                    let i = i as f64;
+                    let x = i * bar_w;
+
+                    let bh = (*v as f64) * (h * 0.85);
+                    let y = h - bh;
+
+                    // Slightly wider than computed bar_w to avoid gaps at small widths.
+                    cr.rectangle(x, y, (bar_w * 2.0).max(1.0), bh.max(1.0));
+                }
+                let _ = cr.fill();
+
+                if draw_handle.style() == VizStyle::PeakHold {
+                    cr.set_source_rgba(r, g, b, 0.35);
+                    for (i, p) in draw_handle.peaks.borrow().iter().enumerate() {
+                        let y = h - (*p as f64) * (h * 0.85);
+                        cr.rectangle(i as f64 * bar_w, y - 2.0, bar_w.max(1.0), 2.0);
+                    }
+                    let _ = cr.fill();
+                }
+            }
+            VizStyle::Mirrored => {
+                cr.set_source_rgba(r, g, b, 0.12);
+                for (i, v) in vals.iter().enumerate() {
+                    let bh = (*v as f64) * (h * 0.85);
+                    let y = (h - bh) / 2.0;
+                    cr.rectangle(i as f64 * bar_w, y, (bar_w * 2.0).max(1.0), bh.max(1.0));
+                }
+                let _ = cr.fill();
+            }
+            VizStyle::Line => {
+                let point = |i: usize, v: f32| {
+                    let x = (i as f64 + 0.5) * bar_w;
+                    (x, h - (v as f64) * (h * 0.85))
+                };
+                cr.move_to(0.0, h);
+                for (i, v) in vals.iter().enumerate() {
+                    let (x, y) = point(i, *v);
+                    cr.line_to(x, y);
+                }
+                cr.line_to(w, h);
+                cr.close_path();
+                let _ = cr.fill();
+
+                cr.set_source_rgba(r, g, b, 0.35);
+                cr.set_line_width(1.5);
+                for (i, v) in vals.iter().enumerate() {
+                    let (x, y) = point(i, *v);
+                    if i == 0 {
+                        cr.move_to(x, y);
+                    } else {
+                        cr.line_to(x, y);
+                    }
+                }
+                let _ = cr.stroke();
+            }
+            VizStyle::Oscilloscope => {
+                let wave = draw_handle.waveform.borrow();
+                if wave.len() < 2 {
+                    return;
+                }
+                let step = w / (wave.len() - 1) as f64;
+                cr.set_source_rgba(r, g, b, 0.35);
+                cr.set_line_width(1.5);
+                for (i, s) in wave.iter().enumerate() {
+                    let x = i as f64 * step;
+                    let y = h / 2.0 - (*s as f64) * (h * 0.45);
+                    if i == 0 {
+                        cr.move_to(x, y);
+                    } else {
+                        cr.line_to(x, y);
+                    }
+                }
+                let _ = cr.stroke();
+            }
+            VizStyle::Off => {}
         }
-
-        let _ = cr.fill();
     });
 
     (area, handle)
 }
 
+// GTK deprecated named color lookups without a replacement
+#[allow(deprecated)]
 fn widget_css_color(widget: &gtk::Widget) -> (f64, f64, f64) {
     // Read the resolved CSS "color" from this widget
     let ctx = widget.style_context();
@@ -1,48 +1,73 @@
-use crate::listen::Listen;
-use crate::meta::{Meta, TrackInfo};
-use crate::station::Station;
+use crate::settings::{
+    CloseAction, Settings, AUTOPLAY, LAST_STATION, LOG_FILE, NOTIFICATIONS, VISUALIZER_STYLE,
+};
+use listenmoe::logging;
+use listenmoe::meta::TrackInfo;
+use listenmoe::player::Player;
+use listenmoe::APP_ID;
 
 use adw::{
     glib,
     gtk::{
         self,
-        gdk::{gdk_pixbuf::Pixbuf, Texture},
-        gio::{Cancellable, MemoryInputStream, Menu},
+        accessible::{Property, State},
+        gio::{Menu, SimpleAction},
         prelude::WidgetExt,
-        ApplicationWindow, Button, GestureClick, HeaderBar, MenuButton, Orientation, Picture,
-        Popover,
+        ApplicationWindow, Button, HeaderBar, MenuButton, Orientation,
     },
     prelude::*,
-    Application, StyleManager, WindowTitle,
+    Application, Breakpoint, BreakpointBin, BreakpointCondition, WindowTitle,
 };
 use gettextrs::gettext;
-use std::{
-    sync::{atomic::Ordering, mpsc},
-    thread,
-    time::Duration,
-};
-
+use log::error;
+#[cfg(target_os = "linux")]
+use log::warn;
+use std::{cell::RefCell, rc::Rc, sync::mpsc};
+
+use super::animation::Animation;
+use super::art::{CoverPopover, CoverResult};
+use super::expanded::ExpandedView;
+use super::export::Exporter;
+use super::live_settings::LiveSettings;
+use super::lookup::LookupMenu;
+use super::lyrics::LyricsPane;
+use super::main_loop::MainLoop;
+use super::notify::{self, NotifyMode};
+use super::preferences::present_preferences;
+use super::progress::TrackProgress;
+use super::server::LocalServer;
+use super::tint::Tinter;
 #[cfg(target_os = "linux")]
-use super::controls::MediaControlEvent;
-use super::{actions, cover, viz};
+use super::tray::build_tray;
+use super::{actions, shortcuts};
 
-const COVER_MAX_SIZE: i32 = 250;
+const EXPANDED_SIZE: (i32, i32) = (360, 640);
+/// Content height (below the titlebar) from which the window switches to the full player.
+const EXPAND_BREAKPOINT: &str = "min-height: 240px";
 const APP_NAME: &str = "Listen Moe";
-const APP_ID: &str = "io.github.noobping.listenmoe";
 
 pub fn build_ui(app: &Application) {
-    let station = Station::Jpop;
-    let radio = Listen::new(station);
-    let spectrum_bits = radio.spectrum_bars();
+    // Activating again (e.g. a second launch) shows the existing, possibly hidden, window
+    if let Some(window) = app.windows().first() {
+        window.present();
+        return;
+    }
+
+    let settings = Settings::load();
+    logging::set_file_enabled(settings.boolean(LOG_FILE));
+    let station = settings.startup_station();
     let (tx, rx) = mpsc::channel::<TrackInfo>();
-    let meta = Meta::new(station, tx, radio.lag_ms());
-    let (cover_tx, cover_rx) = mpsc::channel::<Result<Vec<u8>, String>>();
+    let player = Player::new(station, tx);
+    let (cover_tx, cover_rx) = mpsc::channel::<CoverResult>();
     let win_title = WindowTitle::new(APP_NAME, &gettext("J-POP and K-POP radio"));
+    let current_track: Rc<RefCell<Option<TrackInfo>>> = Rc::new(RefCell::new(None));
 
     let play_button = Button::from_icon_name("media-playback-start-symbolic");
     play_button.set_action_name(Some("win.play"));
+    set_label(&play_button, &gettext("Play"));
     let pause_button = Button::from_icon_name("media-playback-pause-symbolic");
     pause_button.set_action_name(Some("win.pause"));
+    set_label(&pause_button, &gettext("Pause"));
     pause_button.set_visible(false);
 
     let height = 50;
@@ -52,286 +77,280 @@ pub fn build_ui(app: &Application) {
         .icon_name(APP_ID)
         .default_width(300)
         .default_height(height)
-        .resizable(false)
         .build();
 
+    let notify_mode =
+        NotifyMode::from_name(&settings.string(NOTIFICATIONS)).unwrap_or(NotifyMode::Unfocused);
+    let notifier = notify::Notifier::new(app, &window, notify_mode);
+    let exporter = Exporter::new(&settings);
+    let server = LocalServer::new(&settings);
+    let mode_action = notify::make_mode_action(&settings);
+    window.add_action(&mode_action);
+
     window.add_css_class("cover-tint");
-    let style_manager = StyleManager::default();
-    style_manager.set_color_scheme(adw::ColorScheme::Default);
-    let css_provider = cover::install_css_provider();
+    let tinter = Tinter::new(settings.tint_mode());
 
     #[cfg(target_os = "linux")]
     let (controls, ctrl_rx) = actions::build_actions(
         &window,
-        &app,
         &win_title,
         &play_button,
         &pause_button,
-        &radio,
-        &meta,
+        &player,
+        &current_track,
     );
     #[cfg(target_os = "linux")]
-    let set_metadata = {
-        let controls = controls.clone();
-        move |title: String, artist: String, art_url: Option<&str>| {
-            if let Some(c) = controls.as_ref() {
-                c.set_metadata(title.as_str(), artist.as_str(), APP_NAME, art_url);
-            }
+    let (tray, tray_rx) = match build_tray(APP_ID, APP_NAME, station) {
+        Ok((tray, tray_rx)) => (Some(tray), Some(tray_rx)),
+        Err(e) => {
+            warn!("Tray icon unavailable: {e}");
+            (None, None)
         }
     };
     #[cfg(not(target_os = "linux"))]
     actions::build_actions(
         &window,
-        &app,
         &win_title,
         &play_button,
         &pause_button,
-        &radio,
-        &meta,
+        &player,
+        &current_track,
     );
 
     // Build UI
     let menu = Menu::new();
-    actions::populate_menu(&window, &play_button, &menu, &radio, &meta);
+    let recent_menu = Menu::new();
+    let lookup = LookupMenu::new();
+    lookup.add_actions(&window);
+    actions::populate_menu(&window, &menu, &recent_menu, lookup.menu(), &player);
     let more_button = MenuButton::builder()
         .icon_name("view-more-symbolic")
-        .tooltip_text("Main Menu")
+        .tooltip_text(gettext("Main Menu"))
         .menu_model(&menu)
         .build();
+    more_button.update_property(&[Property::Label(&gettext("Main Menu"))]);
     let buttons = gtk::Box::new(Orientation::Horizontal, 0);
     buttons.append(&more_button);
     buttons.append(&play_button);
     buttons.append(&pause_button);
     let header = HeaderBar::new();
     header.pack_start(&buttons);
-    header.set_title_widget(Some(&win_title));
+    // The title doubles as the cover button, so it is reachable from the keyboard
+    let cover_button = Button::builder().child(&win_title).build();
+    cover_button.add_css_class("flat");
+    cover_button.update_property(&[Property::Label(&gettext("Show cover"))]);
+    header.set_title_widget(Some(&cover_button));
     header.set_show_title_buttons(false);
     header.add_css_class("cover-tint");
     header.set_height_request(height);
 
-    let art_picture = Picture::builder()
-        .can_shrink(true)
-        .focusable(false)
-        .sensitive(false)
-        .build();
-    let art_popover = Popover::builder()
-        .has_arrow(true)
-        .position(gtk::PositionType::Bottom)
-        .autohide(true)
-        .child(&art_picture)
-        .build();
-    art_popover.set_parent(&header);
-    art_popover.add_css_class("cover-tint");
-    let title_click = GestureClick::new();
+    let art = CoverPopover::new(&cover_button, cover_tx);
+    art.add_actions(&window);
     {
-        let picture = art_picture.clone();
-        let art = art_popover.clone();
-        title_click.connect_released(move |_, _, _, _| {
-            if art.is_visible() {
-                art.popdown();
-            } else if picture.paintable().is_some() {
-                art.popup();
-            }
-        });
-    }
-    win_title.add_controller(title_click);
-    let close_any_click = GestureClick::new();
-    {
-        let art = art_popover.clone();
-        close_any_click.connect_released(move |_, _, _, _| {
-            art.popdown();
-        });
+        let art = art.clone();
+        cover_button.connect_clicked(move |_| art.toggle());
     }
-    art_popover.add_controller(close_any_click);
 
     let close_btn = Button::from_icon_name("window-close-symbolic");
-    close_btn.set_action_name(Some("win.quit"));
+    close_btn.set_action_name(Some("window.close"));
+    set_label(&close_btn, &gettext("Close"));
     header.pack_end(&close_btn);
+    let expand_button = Button::from_icon_name("pan-down-symbolic");
+    expand_button.set_action_name(Some("win.expand"));
+    set_label(&expand_button, &gettext("Expanded view"));
+    expand_button.update_state(&[State::Expanded(Some(false))]);
+    header.pack_end(&expand_button);
 
     let overlay = gtk::Overlay::new();
     overlay.add_css_class("titlebar-tint");
     overlay.set_height_request(height);
 
+    let expanded = Rc::new(ExpandedView::new(48));
     // Create bars visualizer and add it behind headerbar
-    let (viz, viz_handle) = viz::make_bars_visualizer(48, height);
-    overlay.set_child(Some(&viz));
+    let animation = Animation::new(&window, &player, &expanded, height);
+    overlay.set_child(Some(animation.widget()));
 
     header.add_css_class("viz-transparent");
     header.add_css_class("cover-tint");
     overlay.add_overlay(&header);
+    let progress = Rc::new(TrackProgress::new());
+    overlay.add_overlay(progress.widget());
     window.set_titlebar(Some(&overlay));
 
-    // Tiny dummy content so GTK can shrink the window
+    // Tiny dummy content so GTK can shrink the window; it only grows when the
+    // full player or the lyrics are shown
     let dummy = gtk::Box::new(Orientation::Vertical, 0);
     dummy.set_height_request(0);
     dummy.set_vexpand(false);
-    window.set_child(Some(&dummy));
-
-    // Poll the channels on the GTK main thread and update the UI.
+    dummy.append(expanded.widget());
+    let lyrics = LyricsPane::new(&player);
+    dummy.append(lyrics.widget());
+    let bin = BreakpointBin::builder()
+        .width_request(300)
+        .height_request(0)
+        .child(&dummy)
+        .build();
+    window.set_child(Some(&bin));
+
+    let set_expanded = {
+        let expanded = expanded.clone();
+        let button = expand_button.clone();
+        move |on: bool| {
+            expanded.set_expanded(on);
+            button.set_icon_name(if on {
+                "pan-up-symbolic"
+            } else {
+                "pan-down-symbolic"
+            });
+            button.update_state(&[State::Expanded(Some(on))]);
+        }
+    };
+    let expand_action = SimpleAction::new_stateful("expand", None, &false.to_variant());
     {
-        let win = win_title.clone();
-        let art_popover = art_popover.clone();
-        let art_picture = art_picture.clone();
-        let cover_rx = cover_rx;
-        let cover_tx = cover_tx.clone();
-        #[cfg(target_os = "linux")]
         let window = window.clone();
-        #[cfg(target_os = "linux")]
-        let set_metadata = set_metadata.clone();
-
-        let clear_art_ui = |art_picture: &gtk::Picture,
-                            art_popover: &gtk::Popover,
-                            style_manager: &adw::StyleManager,
-                            css_provider: &gtk::CssProvider| {
-            // Clear old cover so it doesn't stick around
-            art_picture.set_paintable(None::<&adw::gdk::Paintable>);
-
-            // Reset the rest of the UI state
-            art_popover.popdown();
-            style_manager.set_color_scheme(adw::ColorScheme::Default);
-            cover::apply_cover_tint_css_clear(css_provider);
-        };
-
-        glib::timeout_add_local(Duration::from_millis(100), move || {
-            #[cfg(target_os = "linux")]
-            if let Some(ctrl_rx) = &ctrl_rx {
-                for event in ctrl_rx.try_iter() {
-                    let _ = match event {
-                        MediaControlEvent::Play => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.play",
-                            None::<&glib::Variant>,
-                        ),
-                        MediaControlEvent::Pause => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.pause",
-                            None::<&glib::Variant>,
-                        ),
-                        MediaControlEvent::Stop => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.stop",
-                            None::<&glib::Variant>,
-                        ),
-                        MediaControlEvent::Toggle => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.toggle",
-                            None::<&glib::Variant>,
-                        ),
-                        MediaControlEvent::Next => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.next_station",
-                            None::<&glib::Variant>,
-                        ),
-                        MediaControlEvent::Previous => adw::prelude::WidgetExt::activate_action(
-                            &window,
-                            "win.prev_station",
-                            None::<&glib::Variant>,
-                        ),
-                    };
-                }
-            }
-
-            for info in rx.try_iter() {
-                win.set_title(&info.artist);
-                win.set_subtitle(&info.title);
-
-                #[cfg(target_os = "linux")]
-                let cover_url = info
-                    .album_cover
-                    .as_ref()
-                    .or(info.artist_image.as_ref())
-                    .map(|s| s.as_str());
-
-                #[cfg(target_os = "linux")]
-                set_metadata(info.title.clone(), info.artist.clone(), cover_url.clone());
-
-                if let Some(url) = info.album_cover.as_ref().or(info.artist_image.as_ref()) {
-                    let tx = cover_tx.clone();
-                    let url = url.to_string();
-                    thread::spawn(move || {
-                        let result =
-                            cover::fetch_cover_bytes_blocking(&url).map_err(|e| e.to_string());
-                        let _ = tx.send(result);
-                    });
-                } else {
-                    clear_art_ui(&art_picture, &art_popover, &style_manager, &css_provider);
-                }
+        let set_expanded = set_expanded.clone();
+        expand_action.connect_activate(move |action, _| {
+            let on = !action.state().and_then(|v| v.get::<bool>()).unwrap_or(false);
+            action.set_state(&on.to_variant());
+            set_expanded(on);
+            let (w, h) = if on { EXPANDED_SIZE } else { (300, height) };
+            window.set_default_size(w, h);
+        });
+    }
+    window.add_action(&expand_action);
+    // Read-only, so `--now-playing` can ask the running instance for the track
+    let now_playing = SimpleAction::new_stateful("now_playing", None, &"".to_variant());
+    now_playing.set_enabled(false);
+    window.add_action(&now_playing);
+    match BreakpointCondition::parse(EXPAND_BREAKPOINT) {
+        Ok(condition) => {
+            let breakpoint = Breakpoint::new(condition);
+            {
+                let action = expand_action.clone();
+                let set_expanded = set_expanded.clone();
+                breakpoint.connect_apply(move |_| {
+                    action.set_state(&true.to_variant());
+                    set_expanded(true);
+                });
             }
-
-            for result in cover_rx.try_iter() {
-                match result {
-                    Ok(bytes_vec) => {
-                        let bytes = glib::Bytes::from_owned(bytes_vec);
-                        let stream = MemoryInputStream::from_bytes(&bytes);
-                        match Pixbuf::from_stream_at_scale(
-                            &stream,
-                            COVER_MAX_SIZE,
-                            COVER_MAX_SIZE,
-                            true,
-                            None::<&Cancellable>,
-                        ) {
-                            Ok(pixbuf) => {
-                                let texture = Texture::for_pixbuf(&pixbuf);
-                                art_picture.set_paintable(Some(&texture));
-
-                                let (r, g, b) = cover::avg_rgb_from_pixbuf(&pixbuf);
-                                let (r, g, b) = cover::boost_saturation(r, g, b, 1.15);
-                                let cover_is_light = cover::is_light_color(r, g, b);
-
-                                style_manager.set_color_scheme(if cover_is_light {
-                                    adw::ColorScheme::ForceLight
-                                } else {
-                                    adw::ColorScheme::ForceDark
-                                });
-
-                                cover::apply_color(&css_provider, (r, g, b), cover_is_light);
-                            }
-                            Err(err) => {
-                                eprintln!("Failed to decode cover pixbuf: {err}");
-                                clear_art_ui(
-                                    &art_picture,
-                                    &art_popover,
-                                    &style_manager,
-                                    &css_provider,
-                                );
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Failed to load cover bytes: {err}");
-                        clear_art_ui(&art_picture, &art_popover, &style_manager, &css_provider);
-                    }
-                }
+            {
+                let action = expand_action.clone();
+                let set_expanded = set_expanded.clone();
+                breakpoint.connect_unapply(move |_| {
+                    action.set_state(&false.to_variant());
+                    set_expanded(false);
+                });
             }
-
-            glib::ControlFlow::Continue
+            bin.add_breakpoint(breakpoint);
+        }
+        Err(err) => error!("Invalid breakpoint condition: {err}"),
+    }
+    window.add_action(&{
+        let action = SimpleAction::new("cycle_visualizer", None);
+        let settings = settings.clone();
+        let animation = animation.clone();
+        action.connect_activate(move |_, _| {
+            settings.set_string(VISUALIZER_STYLE, animation.style().next().name());
+        });
+        action
+    });
+    window.add_action(&{
+        let action = SimpleAction::new("preferences", None);
+        let win = window.clone();
+        let settings = settings.clone();
+        action.connect_activate(move |_, _| present_preferences(&win, &settings));
+        action
+    });
+    window.add_action(&{
+        let action = SimpleAction::new("shortcuts", None);
+        let win = window.clone();
+        let settings = settings.clone();
+        action.connect_activate(move |_, _| {
+            shortcuts::shortcuts_window(&win, &shortcuts::load(&settings)).present();
         });
+        action
+    });
+    shortcuts::add_play_keys(&window);
+    window.add_action(&{
+        let lyrics = lyrics.clone();
+        let action = SimpleAction::new_stateful("lyrics", None, &false.to_variant());
+        action.connect_activate(move |action, _| {
+            let show = !action.state().and_then(|v| v.get::<bool>()).unwrap_or(false);
+            action.set_state(&show.to_variant());
+            lyrics.set_revealed(show);
+        });
+        action
+    });
+
+    LiveSettings {
+        app: app.clone(),
+        tinter: tinter.clone(),
+        animation,
+        notifier: notifier.clone(),
+        mode_action,
+        exporter: exporter.clone(),
+        server: server.clone(),
+        player: player.clone(),
+        current_track: current_track.clone(),
     }
-
-    // music animation
+    .connect(&settings);
     {
-        let viz = viz.clone();
-        let handle = viz_handle.clone();
-        let spectrum_bits = spectrum_bits.clone();
-
-        // UI-side smoothing (optional)
-        let mut smooth = vec![0.0f32; spectrum_bits.len()];
-
-        glib::timeout_add_local(Duration::from_millis(33), move || {
-            let mut bars = vec![0.0f32; spectrum_bits.len()];
-            for i in 0..bars.len() {
-                bars[i] = f32::from_bits(spectrum_bits[i].load(Ordering::Relaxed)).clamp(0.0, 1.0);
+        let settings = settings.clone();
+        window.connect_close_request(move |window| {
+            if settings.close_action() == CloseAction::Background {
+                window.set_visible(false);
+                return glib::Propagation::Stop;
             }
-
-            for i in 0..bars.len() {
-                smooth[i] = smooth[i] * 0.70 + bars[i] * 0.30;
-            }
-
-            handle.set_values(&smooth);
-            viz.queue_draw();
-            glib::ControlFlow::Continue
+            glib::Propagation::Proceed
         });
     }
+    {
+        let settings = settings.clone();
+        let player = player.clone();
+        app.connect_shutdown(move |_| {
+            settings.set_string(LAST_STATION, player.station().name());
+        });
+    }
+
+    MainLoop {
+        window: window.clone(),
+        title: win_title,
+        cover_button,
+        player,
+        current_track,
+        notifier,
+        exporter,
+        server,
+        lyrics,
+        recent_menu,
+        lookup,
+        expanded,
+        progress,
+        art,
+        tinter,
+        now_playing,
+        #[cfg(target_os = "linux")]
+        controls,
+        #[cfg(target_os = "linux")]
+        tray,
+        tracks: rx,
+        covers: cover_rx,
+        #[cfg(target_os = "linux")]
+        media_events: ctrl_rx,
+        #[cfg(target_os = "linux")]
+        tray_events: tray_rx,
+    }
+    .start();
 
     window.present();
+    if settings.boolean(AUTOPLAY) {
+        let _ =
+            adw::prelude::WidgetExt::activate_action(&window, "win.play", None::<&glib::Variant>);
+    }
+}
+
+/// Tooltip and accessible name for an icon-only button.
+fn set_label(button: &Button, label: &str) {
+    button.set_tooltip_text(Some(label));
+    button.update_property(&[Property::Label(label)]);
 }